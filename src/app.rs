@@ -1,30 +1,86 @@
 
 
+use std::collections::{BTreeSet, HashMap};
 use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::time::Instant;
+use crossterm::clipboard::CopyToClipboard;
 use crossterm::event::KeyCode;
+use crossterm::execute;
 use ratatui::layout::{Constraint, Direction, Layout};
 use ratatui::style::palette::material::BLUE;
 use ratatui::style::Color;
+use ratatui::text::Line;
 use ratatui::widgets::{Block, Borders, Paragraph};
 use ratatui::Frame;
+use crate::components::batch::{render_batch, BatchPreview, BatchRow};
 use crate::components::component_base::FocusableWidget;
+use crate::components::dry_run::{render_dry_run, DryRun};
 use crate::components::file_browser::FileBrowser;
+use crate::components::history::{render_history, HistoryView};
+use crate::components::jobs::JobsView;
+use crate::components::prompt::{render_prompt, Prompt, PromptAction};
+use crate::components::remux_progress::RemuxProgressView;
+use crate::components::text_input::{InputPurpose, InputResult, TextInput};
+use crate::config::Config;
 use crate::components::video_info::VideoInfo; // Assuming VideoInfo is in this module
 use crate::events::events::AppEvent;
+use crate::ffmpeg::command_line;
+use crate::ffmpeg::progress::RemuxProgress;
+use crate::ffmpeg::runner::{self, RemuxEvent, RemuxHandle};
+use crate::ffmpeg::probe;
+use crate::jobs::failure::{Failure, Fix};
+use crate::jobs::history::{self, HistoryEntry};
+use crate::jobs::job::{Job, JobId, JobKind, JobStatus, remove_partial_outputs};
+use crate::jobs::queue::JobQueue;
+use crate::remux::concat;
+use crate::remux::conflict::{self, Conflict, ConflictPolicy};
+use crate::remux::edit::TrackEdit;
+use crate::remux::extract::{self, ExtractPlan};
+use crate::remux::plan::RemuxPlan;
+use crate::remux::preflight::{self, Claim, Reserved};
+use crate::remux::rules;
+use crate::remux::sidecar;
+use crate::remux::split::{self, SplitMode};
+use crate::remux::transcode::{AudioTranscode, SubtitleFormat};
+use crate::remux::trim;
+use crate::matroska::header::{self, HeaderEdit};
+use crate::mp4;
+use crate::remux::in_place;
+use crate::remux::verify::{self, Verdict};
+use crate::remux::template;
+use crate::scan::{self, ScanEvent, ScanFilter, ScanProgress, ScanHandle, ScanId};
 use crate::state::AppState;
 use crate::focus_manager::FocusManager;
+use crate::theme::theme::Theme;
 
 const APP_BACKGROUND: Color = BLUE.c900;
 pub struct App {
     file_browser: FileBrowser,
     video_info: VideoInfo,
+    remux_progress: RemuxProgressView,
+    jobs_view: JobsView,
     main_layout: Layout,
     state: AppState,
     focus: FocusManager,
+    sender: Sender<AppEvent>,
+    /// ffmpeg processes of the running jobs.
+    handles: HashMap<JobId, RemuxHandle>,
+    /// Space held by the running jobs.
+    claims: HashMap<JobId, Vec<Claim>>,
+    scan_handle: Option<ScanHandle>,
+    /// Scans started so far, the last one being the only one whose events count.
+    scans: ScanId,
+    should_quit: bool,
+    theme: Theme,
+    config: Config,
 }
 
 impl App {
-    pub fn new() -> Self {
+    pub fn new(sender: Sender<AppEvent>) -> Self {
 
         let main_layout = Layout::default()
         
@@ -34,8 +90,37 @@ impl App {
             Constraint::Percentage(50),
         ]);
     
+        let (jobs, queue_error) = match JobQueue::load() {
+            Ok(jobs) => (jobs, None),
+            Err(err) => (JobQueue::default(), Some(err)),
+        };
         let state = AppState {
             selected_file: None,
+            marked_files: BTreeSet::new(),
+            probes: HashMap::new(),
+            scan: None,
+            status: queue_error,
+            jobs,
+            current_job: None,
+            job_cursor: 0,
+            prompt: None,
+            dry_run: None,
+            batch: None,
+            history: None,
+            text_input: None,
+            probe: None,
+            output_preview: None,
+            sidecars: vec![],
+            mux_sidecars: false,
+            faststart: None,
+            stream_cursor: 0,
+            selected_streams: BTreeSet::new(),
+            trim: vec![],
+            audio: vec![],
+            split: None,
+            subtitles: None,
+            in_place: false,
+            rules: vec![],
         };
 
         let mut file_browser = FileBrowser::new(env::current_dir().unwrap_or_else(|_| {
@@ -47,13 +132,214 @@ impl App {
         let mut video_info = VideoInfo::new();
         video_info.set_focus(false);
 
-        App { file_browser, video_info, main_layout, state, focus: FocusManager::new(3) }
+        let remux_progress = RemuxProgressView::new();
+        let jobs_view = JobsView::new();
+
+        let config = Config::load();
+        let mut state = state;
+        match rules::parse_all(&config.track_rules) {
+            Ok(rules) => state.rules = rules,
+            Err(err) => state.status = Some(format!("Track rules ignored: {err}")),
+        }
+
+        let mut app = App {
+            file_browser,
+            video_info,
+            remux_progress,
+            jobs_view,
+            main_layout,
+            state,
+            focus: FocusManager::new(4),
+            sender,
+            handles: HashMap::new(),
+            claims: HashMap::new(),
+            scan_handle: None,
+            scans: 0,
+            should_quit: false,
+            theme: Theme::default(),
+            config,
+        };
+        app.resume_queue();
+        app
+    }
+
+    /// Cleans up after jobs a crash interrupted and offers to go on with the pending ones.
+    fn resume_queue(&mut self) {
+        let (mut interrupted, mut recovered) = (0, 0);
+        for job in self.state.jobs.jobs.iter_mut().filter(|j| j.status == JobStatus::Running) {
+            if let (true, Some(plan), Some(verdict)) = (job.replacing, job.plan(), &job.verdict) {
+                // The swap was under way; the temporary output is gone once the rename is done.
+                let finished = match plan.output.exists() {
+                    true => finish_in_place(plan, verdict, &self.config),
+                    false => Ok(format!("Replaced {} before the interruption", plan.input.display())),
+                };
+                (job.status, job.message) = match finished {
+                    Ok(message) => (JobStatus::Done, Some(message)),
+                    Err(message) => (JobStatus::Failed, Some(message)),
+                };
+                job.replacing = false;
+                recovered += 1;
+                continue;
+            }
+            // A crash right after the swap of an older queue leaves a plan made for the
+            // original; its stream indexes may not fit the new file.
+            if let Some(plan) = job.plan().filter(|p| p.in_place)
+                && !probe::probe(&plan.input).is_ok_and(|probe| probe.matches(&plan.source))
+            {
+                remove_partial_outputs(&job.outputs());
+                let message = format!("{} changed since the job was planned, plan it again", plan.input.display());
+                job.status = JobStatus::Failed;
+                job.message = Some(message);
+                interrupted += 1;
+            }
+        }
+        // The others run again, unless their output was complete and only the bookkeeping was lost.
+        let (reset, verified) = self.state.jobs.reset_interrupted();
+        (interrupted, recovered) = (interrupted + reset, recovered + verified);
+        if interrupted + recovered > 0 {
+            self.save_jobs();
+        }
+        let pending = self.state.jobs.pending();
+        if pending == 0 {
+            self.state.jobs.held = false;
+            return;
+        }
+        let message = match interrupted {
+            0 => format!("{pending} job(s) are pending from the last session. Start them?"),
+            n => format!(
+                "The last batch was interrupted: {n} job(s) cleaned up, {pending} pending. Resume it? Jobs already done are not run again."
+            ),
+        };
+        self.state.prompt = Some(Prompt { message, action: PromptAction::ResumeBatch });
+    }
+
+    /// Returns false when running jobs need confirmation before leaving.
+    fn request_quit(&mut self) -> bool {
+        if self.handles.is_empty() {
+            return true;
+        }
+        if self.state.prompt.is_none() {
+            self.state.prompt = Some(Prompt {
+                message: format!(
+                    "{} job(s) still running. Cancel them and quit? They stay in the queue.",
+                    self.handles.len()
+                ),
+                action: PromptAction::CancelRemuxAndQuit,
+            });
+        }
+        false
+    }
+
+    pub fn should_quit(&self) -> bool {
+        self.should_quit
     }
 
     pub fn handle_event(&mut self, event: &AppEvent) {
 
-        if let AppEvent::Input(key) = event {
+        if let (Some(_), AppEvent::Input(key)) = (&self.state.prompt, event) {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => {
+                    if let Some(prompt) = self.state.prompt.take() {
+                        self.confirm(prompt.action);
+                    }
+                }
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                    if let Some(prompt) = self.state.prompt.take() {
+                        self.decline(prompt.action);
+                    }
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        if let (Some(input), AppEvent::Input(key)) = (self.state.text_input.as_mut(), event) {
+            match input.handle_key(key) {
+                InputResult::Editing => {}
+                InputResult::Cancelled => self.state.text_input = None,
+                InputResult::Submitted(value) => {
+                    let purpose = input.purpose;
+                    self.state.text_input = None;
+                    self.apply_input(purpose, value);
+                }
+            }
+            return;
+        }
+
+        if let (Some(history), AppEvent::Input(key)) = (self.state.history.as_mut(), event) {
+            if !history.handle_key(key) {
+                self.state.history = None;
+            }
+            return;
+        }
+
+        if let (Some(batch), AppEvent::Input(key)) = (self.state.batch.as_mut(), event) {
             match key.code {
+                KeyCode::Up => batch.move_cursor(-1),
+                KeyCode::Down => batch.move_cursor(1),
+                KeyCode::Char(' ') => batch.toggle(),
+                KeyCode::Char('o') => {
+                    if let Some(Ok(plan)) = batch.current().map(|r| &r.plan) {
+                        let output = plan.output.display().to_string();
+                        self.state.text_input = Some(TextInput::new("Output of this file", output, InputPurpose::BatchOutput));
+                    }
+                }
+                KeyCode::Char('k') => {
+                    if let Some(Ok(plan)) = batch.current().map(|r| &r.plan) {
+                        let kept: Vec<String> = plan
+                            .source
+                            .streams
+                            .iter()
+                            .filter(|s| !plan.dropped.contains(&s.index))
+                            .map(|s| s.index.to_string())
+                            .collect();
+                        self.state.text_input =
+                            Some(TextInput::new("Streams kept in this file", kept.join(", "), InputPurpose::BatchStreams));
+                    }
+                }
+                KeyCode::Enter => {
+                    if let Some(batch) = self.state.batch.take() {
+                        self.queue_batch(batch);
+                    }
+                }
+                KeyCode::Esc | KeyCode::Char('q') => self.state.batch = None,
+                _ => {}
+            }
+            return;
+        }
+
+        if let (Some(dry_run), AppEvent::Input(key)) = (&self.state.dry_run, event) {
+            match key.code {
+                KeyCode::Enter => {
+                    let plan = dry_run.plan.clone();
+                    self.state.dry_run = None;
+                    self.run_plan(plan);
+                }
+                KeyCode::Char('y') => {
+                    let command = dry_run.shell_command();
+                    self.state.status = Some(match execute!(io::stdout(), CopyToClipboard::to_clipboard_from(command)) {
+                        Ok(()) => "Command copied to clipboard".to_string(),
+                        Err(err) => format!("Failed to copy command: {err}"),
+                    });
+                }
+                KeyCode::Char('s') => self.save_script(false),
+                KeyCode::Char('p') => {
+                    let mut plan = dry_run.plan.clone();
+                    plan.conflict_policy = Some(dry_run.policy.next());
+                    self.state.dry_run = Some(DryRun::new(plan, &self.config, &self.reserved()));
+                }
+                KeyCode::Esc | KeyCode::Char('q') => self.state.dry_run = None,
+                _ => {}
+            }
+            return;
+        }
+
+        match event {
+            AppEvent::Input(key) => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => {
+                    self.should_quit = self.request_quit();
+                    return;
+                }
                 KeyCode::Tab => {
                     self.focus.next();
                     return;
@@ -62,10 +348,189 @@ impl App {
                     self.focus.previous();
                     return;
                 }
+                KeyCode::Char('r') => {
+                    if let Some(plan) = self.selected_plan() {
+                        self.run_plan(plan);
+                    }
+                    return;
+                }
+                KeyCode::Char('d') => {
+                    if let Some(plan) = self.selected_plan() {
+                        self.state.dry_run = Some(DryRun::new(plan, &self.config, &self.reserved()));
+                    }
+                    return;
+                }
+                KeyCode::Char('c') => {
+                    if let Some(handle) = self.scan_handle.take() {
+                        // The walker stops at its next file, what it sends until then is dropped.
+                        handle.cancel();
+                        if let Some(scan) = self.state.scan.as_mut() {
+                            scan.running = false;
+                            let root = scan.root.display();
+                            self.state.status = Some(format!("Scan of {root} cancelled: {}", scan.describe()));
+                        }
+                        return;
+                    }
+                    self.cancel_jobs();
+                    return;
+                }
+                KeyCode::Char('j') => {
+                    if let Some(plan) = self.concat_plan() {
+                        self.state.dry_run = Some(DryRun::new(plan, &self.config, &self.reserved()));
+                    }
+                    return;
+                }
+                KeyCode::Char('x') => {
+                    self.extract_streams();
+                    return;
+                }
+                KeyCode::Char('a') => {
+                    self.state.mux_sidecars = !self.state.mux_sidecars;
+                    return;
+                }
+                KeyCode::Char('o') => {
+                    self.config.conflict_policy = self.config.conflict_policy.next();
+                    self.state.status = Some(match self.config.save() {
+                        Ok(()) => format!("When the output exists: {}", self.config.conflict_policy.label()),
+                        Err(err) => format!("Failed to save config: {err}"),
+                    });
+                    return;
+                }
+                KeyCode::Char('k') => {
+                    let ranges = self
+                        .state
+                        .trim
+                        .iter()
+                        .map(|r| {
+                            let end = r.requested.end.map(trim::format_time).unwrap_or_default();
+                            format!("{}-{end}", trim::format_time(r.requested.start))
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    self.state.text_input = Some(TextInput::new("Keep time ranges", ranges, InputPurpose::TrimRanges));
+                    return;
+                }
+                KeyCode::Char('e') => {
+                    let streams = self.target_streams();
+                    let Some(first) = streams.first() else {
+                        return;
+                    };
+                    let value = self
+                        .state
+                        .audio
+                        .iter()
+                        .find(|t| t.stream == *first)
+                        .map(AudioTranscode::to_spec)
+                        .unwrap_or_default();
+                    self.state.text_input =
+                        Some(TextInput::new("Transcode audio", value, InputPurpose::AudioTranscode));
+                    return;
+                }
+                KeyCode::Char('h') => {
+                    let Some(stream) = self.target_streams().first().and_then(|index| {
+                        self.state.probe.as_ref()?.streams.iter().find(|s| s.index == *index)
+                    }) else {
+                        return;
+                    };
+                    // Prefilling from one stream would copy its values onto the others.
+                    let value = if self.target_streams().len() == 1 { TrackEdit::spec_of(stream) } else { String::new() };
+                    self.state.text_input = Some(TextInput::new("Edit track header", value, InputPurpose::TrackEdit));
+                    return;
+                }
+                KeyCode::Char('f') => {
+                    self.optimize_for_streaming();
+                    return;
+                }
+                KeyCode::Char('i') => {
+                    self.state.in_place = !self.state.in_place;
+                    self.state.status = Some(if self.state.in_place {
+                        format!("In place: the input is replaced once verified ({})", self.config.backup.label())
+                    } else {
+                        "Writing to the output template".to_string()
+                    });
+                    return;
+                }
+                KeyCode::Char('v') => {
+                    self.state.subtitles = SubtitleFormat::next(self.state.subtitles);
+                    self.state.status = Some(match self.state.subtitles {
+                        Some(format) => format!("Text subtitles converted to {}", format.label()),
+                        None => "Subtitles copied when the container allows it".to_string(),
+                    });
+                    return;
+                }
+                KeyCode::Char('s') => {
+                    let value = self.state.split.map(|s| s.to_input()).unwrap_or_default();
+                    self.state.text_input = Some(TextInput::new("Split output", value, InputPurpose::Split));
+                    return;
+                }
+                KeyCode::Char('l') => {
+                    self.state.text_input = Some(TextInput::new(
+                        "Track rules",
+                        self.config.track_rules.join("; "),
+                        InputPurpose::TrackRules,
+                    ));
+                    return;
+                }
+                KeyCode::Char('S') => {
+                    self.state.text_input =
+                        Some(TextInput::new("Scan folder", self.config.scan.to_spec(), InputPurpose::Scan));
+                    return;
+                }
+                KeyCode::Char('H') => {
+                    self.state.history = Some(HistoryView::load());
+                    return;
+                }
+                KeyCode::Char('F') => {
+                    self.propose_fixes();
+                    return;
+                }
+                KeyCode::Char('b') => {
+                    self.run_batch();
+                    return;
+                }
+                KeyCode::Char('t') => {
+                    self.state.text_input = Some(TextInput::new(
+                        "Output template",
+                        self.config.output_template.clone(),
+                        InputPurpose::OutputTemplate,
+                    ));
+                    return;
+                }
+                _ => {}
+            },
+            AppEvent::Custom(message) => {
+                self.state.status = Some(message.clone());
+                return;
+            }
+            AppEvent::Remux(id, remux_event) => {
+                self.handle_remux_event(*id, remux_event);
+                return;
+            }
+            AppEvent::Scan(id, scan_event) => {
+                self.handle_scan_event(*id, scan_event);
+                return;
+            }
+            AppEvent::Tick => {}
+        }
+
+        if self.focus.current() == 3
+            && let AppEvent::Input(key) = event
+        {
+            match key.code {
+                KeyCode::Enter => {
+                    self.start_or_retry_job();
+                    return;
+                }
+                KeyCode::Delete | KeyCode::Backspace => {
+                    self.cancel_or_remove_job();
+                    return;
+                }
                 _ => {}
             }
         }
 
+        let selected_file = self.state.selected_file.clone();
+
         // Delegate input to the focused widget
         match self.focus.current() {
             0 => {
@@ -76,8 +541,936 @@ impl App {
                 self.video_info.set_focus(true);
                 self.video_info.handle_event(event, &mut self.state)
             },
+            2 => {
+                self.remux_progress.set_focus(true);
+                self.remux_progress.handle_event(event, &mut self.state)
+            },
+            3 => {
+                self.jobs_view.set_focus(true);
+                self.jobs_view.handle_event(event, &mut self.state)
+            },
             _ => {}
         }
+
+        if self.state.selected_file != selected_file {
+            self.refresh_selection();
+        }
+    }
+
+    /// Probes the newly selected file and previews where it would be written.
+    fn refresh_selection(&mut self) {
+        let file = self
+            .state
+            .selected_file
+            .as_ref()
+            .map(PathBuf::from)
+            .filter(|path| path.is_file());
+        self.state.probe = file.as_ref().and_then(|path| probe::probe(path).ok());
+        self.state.faststart = file.as_ref().and_then(|path| mp4::is_faststart(path).ok().flatten());
+        self.state.stream_cursor = 0;
+        self.state.selected_streams.clear();
+        self.state.trim.clear();
+        self.state.audio.clear();
+        self.state.sidecars = match (&file, &self.state.probe) {
+            (Some(path), Some(_)) => sidecar::detect(path),
+            _ => vec![],
+        };
+        self.refresh_output_preview();
+    }
+
+    fn refresh_output_preview(&mut self) {
+        self.state.output_preview = match (&self.state.selected_file, &self.state.probe) {
+            (Some(file), Some(probe)) => Some(template::render(
+                &self.config.output_template,
+                Path::new(file),
+                probe,
+                &self.config.container,
+            )),
+            _ => None,
+        };
+    }
+
+    fn apply_input(&mut self, purpose: InputPurpose, value: String) {
+        match purpose {
+            InputPurpose::OutputTemplate => {
+                self.config.output_template = value;
+                self.refresh_output_preview();
+                self.state.status = Some(match self.config.save() {
+                    Ok(()) => "Output template saved".to_string(),
+                    Err(err) => format!("Failed to save config: {err}"),
+                });
+            }
+            InputPurpose::TrimRanges => {
+                let Some(file) = self.state.selected_file.clone() else {
+                    return;
+                };
+                let has_video = self
+                    .state
+                    .probe
+                    .as_ref()
+                    .is_some_and(|p| p.streams_of("video").any(|s| s.disposition.attached_pic == 0));
+                match trim::parse_ranges(&value).and_then(|ranges| trim::snap(Path::new(&file), &ranges, has_video)) {
+                    Ok(ranges) => {
+                        let off_keyframe: Vec<String> = ranges
+                            .iter()
+                            .filter(|r| !r.on_keyframe)
+                            .map(|r| trim::format_time(r.start))
+                            .collect();
+                        self.state.status = Some(if ranges.is_empty() {
+                            "Trim cleared".to_string()
+                        } else if !off_keyframe.is_empty() {
+                            format!(
+                                "No keyframe found before {}, the output may start with broken frames there",
+                                off_keyframe.join(", ")
+                            )
+                        } else if ranges.iter().any(|r| r.offset() > 0.001) {
+                            "Some cuts were moved back to the previous keyframe".to_string()
+                        } else {
+                            format!("Keeping {} range(s)", ranges.len())
+                        });
+                        self.state.trim = ranges;
+                    }
+                    Err(err) => self.state.status = Some(format!("Invalid ranges: {err}")),
+                }
+            }
+            InputPurpose::AudioTranscode => {
+                let Some(probe) = &self.state.probe else {
+                    return;
+                };
+                let streams: Vec<_> = self
+                    .target_streams()
+                    .into_iter()
+                    .filter_map(|index| probe.streams.iter().find(|s| s.index == index))
+                    .filter(|s| s.codec_type() == "audio")
+                    .collect();
+                if streams.is_empty() {
+                    self.state.status = Some("Select audio streams to transcode".to_string());
+                    return;
+                }
+                self.state.audio.retain(|t| !streams.iter().any(|s| s.index == t.stream));
+                if value.trim().is_empty() {
+                    self.state.status = Some(format!("{} stream(s) will be copied", streams.len()));
+                    return;
+                }
+                match streams.iter().map(|s| AudioTranscode::parse(s, &value)).collect::<Result<Vec<_>, _>>() {
+                    Ok(transcodes) => {
+                        self.state.status = Some(format!(
+                            "{} stream(s) will be transcoded to {}",
+                            transcodes.len(),
+                            transcodes[0].describe()
+                        ));
+                        self.state.audio.extend(transcodes);
+                        self.state.audio.sort_by_key(|t| t.stream);
+                    }
+                    Err(err) => self.state.status = Some(format!("Invalid transcode: {err}")),
+                }
+            }
+            InputPurpose::TrackEdit => self.edit_tracks(&value),
+            InputPurpose::BatchOutput | InputPurpose::BatchStreams => self.edit_batch_row(purpose, &value),
+            InputPurpose::Scan => match ScanFilter::parse(&value) {
+                Ok(filter) => {
+                    self.config.scan = filter;
+                    if let Err(err) = self.config.save() {
+                        self.state.status = Some(format!("Failed to save config: {err}"));
+                    }
+                    self.start_scan();
+                }
+                Err(err) => self.state.status = Some(format!("Invalid scan filter: {err}")),
+            },
+            InputPurpose::TrackRules => {
+                let texts: Vec<String> =
+                    value.split(';').map(str::trim).filter(|t| !t.is_empty()).map(String::from).collect();
+                match rules::parse_all(&texts) {
+                    Ok(parsed) => {
+                        self.state.rules = parsed;
+                        self.config.track_rules = texts;
+                        self.state.status = Some(match self.config.save() {
+                            Ok(()) if self.state.rules.is_empty() => "Track rules cleared".to_string(),
+                            Ok(()) => format!("{} track rule(s) saved", self.state.rules.len()),
+                            Err(err) => format!("Failed to save config: {err}"),
+                        });
+                    }
+                    Err(err) => self.state.status = Some(format!("Invalid rule: {err}")),
+                }
+            }
+            InputPurpose::Split => match SplitMode::parse(&value) {
+                Ok(split) => {
+                    let check = match (&split, &self.state.probe) {
+                        (Some(mode), Some(probe)) => mode.segment_args(probe).err(),
+                        _ => None,
+                    };
+                    self.state.status = Some(match (&split, check) {
+                        (None, _) => "Split cleared".to_string(),
+                        (Some(mode), None) => format!("Output split: {}", mode.describe()),
+                        (Some(mode), Some(err)) => format!("Output split: {}, but {err}", mode.describe()),
+                    });
+                    self.state.split = split;
+                }
+                Err(err) => self.state.status = Some(format!("Invalid split: {err}")),
+            },
+        }
+    }
+
+    fn selected_plan(&mut self) -> Option<RemuxPlan> {
+        let input = self.state.selected_file.as_ref().map(PathBuf::from)?;
+        if !input.is_file() {
+            self.state.status = Some(format!("{} is not a file", input.display()));
+            return None;
+        }
+        let probe = match self.state.probe.clone() {
+            Some(probe) => probe,
+            None => match probe::probe(&input) {
+                Ok(probe) => probe,
+                Err(err) => {
+                    self.state.status = Some(format!("ffprobe failed: {err}"));
+                    return None;
+                }
+            },
+        };
+        let mut plan = match self.file_plan(input, probe) {
+            Ok(plan) => plan,
+            Err(err) => {
+                self.state.status = Some(err);
+                return None;
+            }
+        };
+        if self.state.mux_sidecars {
+            plan.sidecars = self.state.sidecars.clone();
+        }
+        if !self.state.trim.is_empty() {
+            plan.segments = trim::segments(&plan.input, &self.state.trim, plan.source.duration());
+            for range in self.state.trim.iter().filter(|r| !r.on_keyframe) {
+                plan.warnings.push(format!(
+                    "cut at {} is not on a keyframe, the output may start with broken frames there",
+                    trim::format_time(range.start)
+                ));
+            }
+        }
+        plan.audio = self.state.audio.clone();
+        plan.validate();
+        Some(plan)
+    }
+
+    /// Plan of one file with the settings shared by every file: output or in-place mode,
+    /// subtitle format, split and track rules.
+    fn file_plan(&self, input: PathBuf, probe: probe::ProbeInfo) -> Result<RemuxPlan, String> {
+        let output = if self.state.in_place {
+            if self.state.split.is_some() {
+                return Err("Cannot split in place".to_string());
+            }
+            in_place::temp_output(&input)
+        } else {
+            template::render(&self.config.output_template, &input, &probe, &self.config.container)
+                .map_err(|err| format!("Invalid output template: {err}"))?
+        };
+        let mut plan = RemuxPlan::new(input, output, probe);
+        plan.in_place = self.state.in_place;
+        plan.faststart = self.config.faststart;
+        plan.subtitles = self.state.subtitles;
+        if let Some(mode) = self.state.split {
+            plan.set_split(mode).map_err(|err| format!("Cannot split: {err}"))?;
+        }
+        rules::apply(&self.state.rules, &mut plan);
+        Ok(plan)
+    }
+
+    /// Plans a remux of every marked file, each against its own probe, for review in the
+    /// batch table.
+    fn run_batch(&mut self) {
+        if self.state.marked_files.is_empty() {
+            self.state.status = Some("Mark files with Space to remux them as a batch".to_string());
+            return;
+        }
+        // One prompt per file would pile up, existing outputs are skipped instead.
+        let policy = match self.config.conflict_policy {
+            ConflictPolicy::Ask => ConflictPolicy::Skip,
+            policy => policy,
+        };
+        let mut rows = vec![];
+        for path in &self.state.marked_files {
+            let probe = match self.state.probes.get(path) {
+                Some(probe) => Ok(probe.clone()),
+                None => probe::probe(path).map_err(|err| format!("ffprobe failed: {err}")),
+            };
+            let plan = probe.and_then(|probe| self.file_plan(path.clone(), probe)).map(|mut plan| {
+                if self.state.mux_sidecars {
+                    plan.sidecars = sidecar::detect(path);
+                }
+                plan.conflict_policy = Some(policy);
+                plan.validate();
+                plan
+            });
+            rows.push(BatchRow::new(path.clone(), plan, self.config.conflict_policy));
+        }
+        self.state.batch = Some(BatchPreview::new(rows));
+    }
+
+    /// Queues the rows of the batch table left included.
+    fn queue_batch(&mut self, batch: BatchPreview) {
+        let included: Vec<BatchRow> = batch.rows.into_iter().filter(|r| r.included).collect();
+        let mut queued = 0;
+        let mut problems = vec![];
+        for row in &included {
+            let Ok(plan) = &row.plan else {
+                continue;
+            };
+            if self.run_plan(plan.clone()) {
+                queued += 1;
+            } else if let Some(status) = self.state.status.take() {
+                problems.push(format!("{}: {status}", row.input.display()));
+            }
+        }
+        self.state.status = Some(match problems.first() {
+            None => format!("Queued {queued} file(s)"),
+            Some(first) => format!("Queued {queued} of {} file(s), {} not queued: {first}", included.len(), problems.len()),
+        });
+    }
+
+    /// Changes the output or the kept streams of the file under the cursor of the batch table.
+    fn edit_batch_row(&mut self, purpose: InputPurpose, value: &str) {
+        let policy = self.config.conflict_policy;
+        let Some(row) = self.state.batch.as_mut().and_then(BatchPreview::current) else {
+            return;
+        };
+        let Ok(plan) = &mut row.plan else {
+            return;
+        };
+        match purpose {
+            InputPurpose::BatchOutput if plan.in_place => {
+                self.state.status = Some("In-place remuxes write over their input".to_string());
+                return;
+            }
+            InputPurpose::BatchOutput => plan.output = PathBuf::from(value.trim()),
+            _ => {
+                let kept = value
+                    .split([',', ' '])
+                    .filter(|v| !v.is_empty())
+                    .map(|v| v.parse::<usize>().map_err(|_| format!("\"{v}\" is not a stream index")))
+                    .collect::<Result<Vec<_>, _>>();
+                match kept {
+                    Ok(kept) => {
+                        plan.dropped =
+                            plan.source.streams.iter().map(|s| s.index).filter(|i| !kept.contains(i)).collect();
+                    }
+                    Err(err) => {
+                        self.state.status = Some(format!("Invalid streams: {err}"));
+                        return;
+                    }
+                }
+            }
+        }
+        plan.warnings.clear();
+        plan.validate();
+        row.refresh(policy);
+        row.included = row.queueable();
+    }
+
+    /// Scans the folder under the cursor, or the one being browsed, for files to add to the batch.
+    fn start_scan(&mut self) {
+        if self.scan_handle.is_some() {
+            self.state.status = Some("A scan is already running".to_string());
+            return;
+        }
+        let Some(selected) = self.state.selected_file.as_ref().map(PathBuf::from) else {
+            return;
+        };
+        let root = match selected.is_dir() {
+            true => selected,
+            false => selected.parent().map(Path::to_path_buf).unwrap_or_default(),
+        };
+        self.state.status = Some(format!("Scanning {} for {}", root.display(), self.config.scan.describe()));
+        self.scans += 1;
+        self.scan_handle = Some(scan::spawn(self.scans, root.clone(), self.config.scan.clone(), self.sender.clone()));
+        self.state.scan = Some(ScanProgress { root, running: true, ..ScanProgress::default() });
+    }
+
+    fn handle_scan_event(&mut self, id: ScanId, event: &ScanEvent) {
+        if self.scan_handle.as_ref().is_none_or(|handle| handle.id != id) {
+            return;
+        }
+        let Some(scan) = self.state.scan.as_mut() else {
+            return;
+        };
+        match event {
+            ScanEvent::Found(found) => scan.found = *found,
+            ScanEvent::Probed(path, probe) => {
+                scan.probed += 1;
+                if let Some(probe) = probe {
+                    scan.added += 1;
+                    self.state.marked_files.insert(path.clone());
+                    self.state.probes.insert(path.clone(), *probe.clone());
+                }
+            }
+            ScanEvent::Finished { cancelled } => {
+                self.scan_handle = None;
+                scan.running = false;
+                self.state.status = Some(format!(
+                    "Scan of {} {}: {}, b queues the marked files",
+                    scan.root.display(),
+                    if *cancelled { "cancelled" } else { "finished" },
+                    scan.describe()
+                ));
+            }
+        }
+    }
+
+    /// Joins the files marked in the browser, in path order.
+    fn concat_plan(&mut self) -> Option<RemuxPlan> {
+        if self.state.marked_files.len() < 2 {
+            self.state.status = Some("Mark at least two files with Space to join them".to_string());
+            return None;
+        }
+        let mut parts = vec![];
+        for path in &self.state.marked_files {
+            match probe::probe(path) {
+                Ok(probe) => parts.push((path.clone(), probe)),
+                Err(err) => {
+                    self.state.status = Some(format!("ffprobe failed on {}: {err}", path.display()));
+                    return None;
+                }
+            }
+        }
+        let (first_path, first_probe) = &parts[0];
+        let output = match template::render(&self.config.output_template, first_path, first_probe, &self.config.container) {
+            Ok(output) => output,
+            Err(err) => {
+                self.state.status = Some(format!("Invalid output template: {err}"));
+                return None;
+            }
+        };
+        let mut plan = concat::plan(parts, output, self.config.concat_chapters);
+        plan.faststart = self.config.faststart;
+        plan.subtitles = self.state.subtitles;
+        plan.validate();
+        if !plan.warnings.is_empty() {
+            self.state.status = Some(format!("{} mismatch(es) between the parts", plan.warnings.len()));
+        }
+        Some(plan)
+    }
+
+    /// Checks the output of a plan and queues it, returning whether it was queued.
+    fn run_plan(&mut self, mut plan: RemuxPlan) -> bool {
+        let policy = plan.conflict_policy.unwrap_or(self.config.conflict_policy);
+        match conflict::apply(&mut plan, policy) {
+            Ok(Conflict::None) => {}
+            Ok(Conflict::Skip) => {
+                self.state.status = Some(format!("Skipped, {} already exists", plan.output.display()));
+                return false;
+            }
+            Ok(Conflict::Ask) => {
+                self.state.prompt = Some(Prompt {
+                    message: format!("{} already exists. Overwrite it?", plan.output.display()),
+                    action: PromptAction::Overwrite(Box::new(plan)),
+                });
+                return false;
+            }
+            Err(err) => {
+                self.state.status = Some(format!("Refusing to remux: {err}"));
+                return false;
+            }
+        }
+
+        let backup_dir = backup_dir(&plan, &self.config);
+        if let Err(err) = preflight::check(&plan, backup_dir.as_deref(), &self.reserved()) {
+            self.state.status = Some(format!("Blocked: {err}"));
+            return false;
+        }
+
+        let warnings = plan.warnings.clone();
+        self.enqueue(JobKind::Remux(Box::new(plan)));
+        if let Some(first) = warnings.first() {
+            self.state.status = Some(format!("{} warning(s), first: {first}", warnings.len()));
+        }
+        true
+    }
+
+    /// Changes language, title or flags of the target streams, in the Matroska header when
+    /// it has room and with a verified in-place remux otherwise.
+    fn edit_tracks(&mut self, value: &str) {
+        let (Some(file), Some(probe)) = (self.state.selected_file.clone(), self.state.probe.clone()) else {
+            return;
+        };
+        let path = PathBuf::from(file);
+        let edits = match self.target_streams().into_iter().map(|i| TrackEdit::parse(i, value)).collect::<Result<Vec<_>, _>>() {
+            Ok(edits) => edits,
+            Err(err) => {
+                self.state.status = Some(format!("Invalid edit: {err}"));
+                return;
+            }
+        };
+        let edits: Vec<TrackEdit> = edits
+            .into_iter()
+            .filter(|e| probe.streams.iter().any(|s| s.index == e.stream && e.changes(s)))
+            .collect();
+        if edits.is_empty() {
+            self.state.status = Some("Nothing to change".to_string());
+            return;
+        }
+
+        let ext = path.extension().unwrap_or_default().to_string_lossy().to_ascii_lowercase();
+        let reason = if matches!(ext.as_str(), "mkv" | "mka" | "mks" | "webm") {
+            let tracks = probe
+                .streams
+                .iter()
+                .filter(|s| s.codec_type() != "attachment" && s.disposition.attached_pic == 0)
+                .count();
+            match header::edit_tracks(&path, &edits, tracks) {
+                Ok(HeaderEdit::Edited) => {
+                    self.state.probe = probe::probe(&path).ok();
+                    self.state.status = Some(format!("Header of {} updated in place", path.display()));
+                    return;
+                }
+                Ok(HeaderEdit::NoRoom) => "no room left in the header".to_string(),
+                Ok(HeaderEdit::NoRoomForTags) => "no room left for the tags of the track".to_string(),
+                Err(err) => err.to_string(),
+            }
+        } else {
+            "not a Matroska file".to_string()
+        };
+
+        let mut plan = RemuxPlan::new(path.clone(), in_place::temp_output(&path), probe);
+        plan.in_place = true;
+        plan.faststart = self.config.faststart;
+        plan.edits = edits;
+        plan.validate();
+        if self.run_plan(plan) {
+            self.state.status = Some(format!("Remuxing {} in place: {reason}", path.display()));
+        }
+    }
+
+    /// Rewrites the selected MP4 in place with its index first.
+    fn optimize_for_streaming(&mut self) {
+        let (Some(file), Some(probe)) = (self.state.selected_file.clone(), self.state.probe.clone()) else {
+            return;
+        };
+        let path = PathBuf::from(file);
+        match self.state.faststart {
+            None => self.state.status = Some(format!("{} is not an MP4 file", path.display())),
+            Some(true) => self.state.status = Some(format!("{} is already optimized for streaming", path.display())),
+            Some(false) => {
+                let mut plan = RemuxPlan::new(path.clone(), in_place::temp_output(&path), probe);
+                plan.in_place = true;
+                plan.faststart = true;
+                plan.validate();
+                self.run_plan(plan);
+            }
+        }
+    }
+
+    /// Streams marked in the info pane, or the one under the cursor.
+    fn target_streams(&self) -> Vec<usize> {
+        let Some(probe) = &self.state.probe else {
+            return vec![];
+        };
+        if self.state.selected_streams.is_empty() {
+            probe.streams.get(self.state.stream_cursor).map(|s| s.index).into_iter().collect()
+        } else {
+            self.state.selected_streams.iter().copied().collect()
+        }
+    }
+
+    /// Saves the command of the dry run as a script next to its output, an existing script
+    /// being handled by the conflict policy of the dry run.
+    fn save_script(&mut self, overwrite: bool) {
+        let Some(dry_run) = &self.state.dry_run else {
+            return;
+        };
+        let mut script = dry_run.script.clone();
+        if script.exists() && !overwrite {
+            match dry_run.policy {
+                ConflictPolicy::Ask => {
+                    self.state.prompt = Some(Prompt {
+                        message: format!("{} already exists. Overwrite it?", script.display()),
+                        action: PromptAction::OverwriteScript,
+                    });
+                    return;
+                }
+                ConflictPolicy::Skip => {
+                    self.state.status = Some(format!("Skipped, {} already exists", script.display()));
+                    return;
+                }
+                ConflictPolicy::Overwrite => {}
+                ConflictPolicy::AutoSuffix => script = conflict::next_free_path(&script),
+            }
+        }
+        let saved = command_line::save_script(&script, &dry_run.files, std::slice::from_ref(&dry_run.argv));
+        self.state.status = Some(match saved {
+            Ok(()) => format!("Command saved to {}", script.display()),
+            Err(err) => format!("Failed to save {}: {err}", script.display()),
+        });
+    }
+
+    fn extract_streams(&mut self) {
+        let streams = self.target_streams();
+        let (Some(file), Some(probe)) = (&self.state.selected_file, &self.state.probe) else {
+            return;
+        };
+
+        match extract::plan(
+            Path::new(file),
+            probe,
+            &streams,
+            &self.config.extract_template,
+            self.config.conflict_policy,
+        ) {
+            Ok((plan, Conflict::None)) => self.run_extract(plan),
+            Ok((_, Conflict::Skip)) => {
+                self.state.status = Some("Skipped, every extracted file already exists".to_string());
+            }
+            Ok((plan, Conflict::Ask)) => {
+                self.state.prompt = Some(Prompt {
+                    message: "Some extracted files already exist or share a name. Overwrite them?".to_string(),
+                    action: PromptAction::OverwriteExtract(Box::new(plan)),
+                });
+            }
+            Err(err) => self.state.status = Some(format!("Cannot extract: {err}")),
+        }
+    }
+
+    fn run_extract(&mut self, plan: ExtractPlan) {
+        if let Some(probe) = &self.state.probe
+            && let Err(err) = preflight::check_extract(&plan, probe, &self.reserved())
+        {
+            self.state.status = Some(format!("Blocked: {err}"));
+            return;
+        }
+        self.enqueue(JobKind::Extract(plan));
+    }
+
+    fn enqueue(&mut self, kind: JobKind) {
+        let id = self.state.jobs.push(kind);
+        self.state.jobs.held = false;
+        self.save_jobs();
+        self.start_jobs();
+        if let Some(job) = self.state.jobs.get(id)
+            && job.status == JobStatus::Pending
+        {
+            self.state.status =
+                Some(format!("Queued {} ({} pending)", job.input().display(), self.state.jobs.pending()));
+        }
+    }
+
+    /// Starts pending jobs in queue order up to `max_jobs`, skipping those that would share
+    /// a disk with a running job when `one_job_per_disk` is set.
+    fn start_jobs(&mut self) {
+        if self.state.jobs.held {
+            return;
+        }
+        let mut busy: BTreeSet<String> = self
+            .state
+            .jobs
+            .jobs
+            .iter()
+            .filter(|j| j.status == JobStatus::Running)
+            .flat_map(Job::disks)
+            .collect();
+        let pending: Vec<JobId> = self.state.jobs.pending_ids();
+        for id in pending {
+            if self.handles.len() >= self.config.max_jobs.max(1) {
+                break;
+            }
+            let Some(job) = self.state.jobs.get(id) else {
+                continue;
+            };
+            let disks = job.disks();
+            if self.config.one_job_per_disk && !busy.is_disjoint(&disks) {
+                continue;
+            }
+            self.start_job(id);
+            if self.handles.contains_key(&id) {
+                busy.extend(disks);
+            }
+        }
+    }
+
+    fn start_job(&mut self, id: JobId) {
+        let Some(job) = self.state.jobs.get(id) else {
+            return;
+        };
+        let outputs = job.outputs();
+        let started = prepare_job(job, &self.config, &self.reserved()).and_then(|claims| {
+            runner::spawn(id, job.ffmpeg_args(), self.sender.clone())
+                .map(|handle| (handle, claims))
+                .map_err(|err| format!("Failed to start ffmpeg: {err}"))
+        });
+        let job = self.state.jobs.get_mut(id).expect("job was just found");
+        match started {
+            Ok((handle, claims)) => {
+                self.handles.insert(id, handle);
+                self.claims.insert(id, claims);
+                job.status = JobStatus::Running;
+                job.started = Some(Instant::now());
+                job.progress = RemuxProgress::default();
+                self.state.current_job = Some(id);
+                self.state.status = Some(format!("Writing {}", display_paths(&outputs)));
+            }
+            Err(err) => {
+                remove_support_files(job);
+                job.status = JobStatus::Failed;
+                job.message = Some(err.clone());
+                self.state.status = Some(err);
+            }
+        }
+        self.save_jobs();
+    }
+
+    /// Stops the batch: running jobs are cancelled and pending ones will not start.
+    fn cancel_jobs(&mut self) {
+        let mut cancelled = 0;
+        for job in self.state.jobs.jobs.iter_mut().filter(|j| j.status == JobStatus::Pending) {
+            job.status = JobStatus::Cancelled;
+            cancelled += 1;
+        }
+        for handle in self.handles.values_mut() {
+            handle.cancel();
+        }
+        if !self.handles.is_empty() {
+            self.state.status = Some("Cancelling remux...".to_string());
+        } else if cancelled > 0 {
+            self.state.status = Some(format!("Cancelled {cancelled} pending job(s)"));
+        }
+        self.save_jobs();
+    }
+
+    fn start_or_retry_job(&mut self) {
+        let Some(id) = self.state.jobs.jobs.get(self.state.job_cursor).map(|j| j.id) else {
+            return;
+        };
+        if self.state.jobs.retry(id) {
+            self.state.status = Some("Job queued again".to_string());
+        }
+        self.state.jobs.held = false;
+        self.save_jobs();
+        self.start_jobs();
+    }
+
+    fn cancel_or_remove_job(&mut self) {
+        let Some(job) = self.state.jobs.jobs.get_mut(self.state.job_cursor) else {
+            return;
+        };
+        let id = job.id;
+        match job.status {
+            JobStatus::Running => {
+                if let Some(handle) = self.handles.get_mut(&id) {
+                    handle.cancel();
+                    self.state.status = Some("Cancelling job...".to_string());
+                }
+            }
+            JobStatus::Pending => job.status = JobStatus::Cancelled,
+            _ => {
+                self.state.jobs.remove(id);
+                if self.state.current_job == Some(id) {
+                    self.state.current_job = None;
+                }
+            }
+        }
+        self.save_jobs();
+    }
+
+    /// Asks before applying the known fixes of every failed job.
+    fn propose_fixes(&mut self) {
+        let mut fixes: Vec<(String, usize)> = vec![];
+        for fix in self.state.jobs.jobs.iter().filter_map(Job::fix) {
+            match fixes.iter_mut().find(|(description, _)| *description == fix.describe()) {
+                Some((_, count)) => *count += 1,
+                None => fixes.push((fix.describe(), 1)),
+            }
+        }
+        if fixes.is_empty() {
+            self.state.status = Some("No failed job has a known fix".to_string());
+            return;
+        }
+        let total: usize = fixes.iter().map(|(_, count)| count).sum();
+        let summary = fixes.iter().map(|(description, count)| format!("{count}x {description}")).collect::<Vec<_>>();
+        self.state.prompt = Some(Prompt {
+            message: format!("Fix {total} failed job(s) and queue them again? {}", summary.join(", ")),
+            action: PromptAction::ApplyFixes,
+        });
+    }
+
+    fn apply_fixes(&mut self) {
+        let (mut fixed, mut skipped) = (0, 0);
+        for job in self.state.jobs.jobs.iter_mut() {
+            let Some(fix) = job.fix() else {
+                continue;
+            };
+            // The failed run got as far as writing the header, so the output is its own.
+            let outputs = job.outputs();
+            let JobKind::Remux(plan) = &mut job.kind else {
+                continue;
+            };
+            let mut fixed_plan = plan.as_ref().clone();
+            fix.apply(&mut fixed_plan);
+            let policy = match fixed_plan.conflict_policy.unwrap_or(self.config.conflict_policy) {
+                ConflictPolicy::Ask => ConflictPolicy::Skip,
+                policy => policy,
+            };
+            if fix == Fix::Matroska && !matches!(conflict::apply(&mut fixed_plan, policy), Ok(Conflict::None)) {
+                job.message = Some(format!("Not fixed, {} exists", fixed_plan.output.display()));
+                skipped += 1;
+                continue;
+            }
+            remove_partial_outputs(&outputs);
+            **plan = fixed_plan;
+            job.reset();
+            job.attempts = 0;
+            fixed += 1;
+        }
+        self.state.jobs.held = false;
+        self.state.status = Some(match skipped {
+            0 => format!("Fixed and queued {fixed} job(s)"),
+            _ => format!("Fixed and queued {fixed} job(s), {skipped} left failed as their new output exists"),
+        });
+        self.save_jobs();
+        self.start_jobs();
+    }
+
+    /// Space the running jobs still need, checked along with the free space for new ones.
+    fn reserved(&self) -> Reserved {
+        preflight::reserved(self.claims.values().flatten())
+    }
+
+    fn save_jobs(&mut self) {
+        if let Err(err) = self.state.jobs.save() {
+            self.state.status = Some(format!("Failed to save the job queue: {err}"));
+        }
+    }
+
+    fn confirm(&mut self, action: PromptAction) {
+        match action {
+            PromptAction::CancelRemuxAndQuit => {
+                // Wait for ffmpeg here, the event loop stops right after.
+                self.claims.clear();
+                for (id, mut handle) in self.handles.drain() {
+                    handle.cancel_and_wait();
+                    if let Some(job) = self.state.jobs.get_mut(id) {
+                        remove_support_files(job);
+                        remove_partial_outputs(&job.outputs());
+                        job.reset();
+                    }
+                }
+                self.save_jobs();
+                self.should_quit = true;
+            }
+            PromptAction::ResumeBatch => {
+                self.state.jobs.held = false;
+                self.start_jobs();
+            }
+            PromptAction::ApplyFixes => self.apply_fixes(),
+            PromptAction::Overwrite(mut plan) => {
+                plan.conflict_policy = Some(ConflictPolicy::Overwrite);
+                self.run_plan(*plan);
+            }
+            PromptAction::OverwriteExtract(mut plan) => {
+                plan.overwrite = true;
+                self.run_extract(*plan);
+            }
+            PromptAction::OverwriteScript => self.save_script(true),
+        }
+    }
+
+    fn decline(&mut self, action: PromptAction) {
+        match action {
+            PromptAction::CancelRemuxAndQuit => {}
+            PromptAction::ResumeBatch => {
+                self.state.status = Some("Batch held, Enter in the Jobs pane starts it".to_string());
+            }
+            PromptAction::ApplyFixes => {}
+            PromptAction::Overwrite(plan) => {
+                self.state.status = Some(format!("Skipped, {} already exists", plan.output.display()));
+            }
+            PromptAction::OverwriteExtract(_) => {
+                self.state.status = Some("Extraction skipped".to_string());
+            }
+            PromptAction::OverwriteScript => {
+                self.state.status = Some("Script not saved".to_string());
+            }
+        }
+    }
+
+    fn handle_remux_event(&mut self, id: JobId, event: &RemuxEvent) {
+        let Some(job) = self.state.jobs.get_mut(id) else {
+            return;
+        };
+        let outcome = match event {
+            RemuxEvent::Progress(progress) => {
+                job.progress = progress.clone();
+                return;
+            }
+            RemuxEvent::Finished(outcome) => outcome,
+        };
+        self.handles.remove(&id);
+        self.claims.remove(&id);
+        remove_support_files(job);
+        job.failure = None;
+        let outputs = job.outputs();
+        let in_place = job.plan().is_some_and(|p| p.in_place);
+        job.verdict = if outcome.success && !outcome.cancelled { job.plan().map(verify::verify) } else { None };
+        if in_place && job.verdict.as_ref().is_some_and(Verdict::passed) {
+            job.replacing = true;
+            self.save_jobs();
+        }
+        let Some(job) = self.state.jobs.get_mut(id) else {
+            return;
+        };
+        let (status, message) = if outcome.cancelled {
+            remove_partial_outputs(&outputs);
+            (JobStatus::Cancelled, format!("Cancelled, removed {}", display_paths(&outputs)))
+        } else if outcome.success {
+            let written: Vec<PathBuf> = outputs.iter().flat_map(|p| split::expand(p)).collect();
+            let finished = match (job.plan(), &job.verdict) {
+                (Some(plan), Some(verdict)) if plan.in_place => finish_in_place(plan, verdict, &self.config),
+                (_, Some(verdict)) if !verdict.passed() => {
+                    Err(format!("Finished, {}: {}", verdict.describe(), display_paths(&written)))
+                }
+                (_, Some(verdict)) => Ok(format!("Finished, {}: {}", verdict.describe(), display_paths(&written))),
+                _ => Ok(format!("Finished: {}", display_paths(&written))),
+            };
+            job.replacing = false;
+            match finished {
+                Ok(message) => (JobStatus::Done, message),
+                Err(message) => (JobStatus::Failed, message),
+            }
+        } else {
+            let failure = Failure::classify(outcome, job.plan(), job.input());
+            job.failure = Some(failure);
+            job.attempts += 1;
+            if in_place {
+                remove_partial_outputs(&outputs);
+                let input = job.input().display();
+                (JobStatus::Failed, format!("Failed ({}), {input} left untouched", failure.describe()))
+            } else {
+                (JobStatus::Failed, format!("Failed ({}): {}", failure.describe(), job.input().display()))
+            }
+        };
+        let replaced = status == JobStatus::Done && in_place;
+        let input = job.input().to_path_buf();
+        job.status = status;
+        job.outcome = Some(outcome.clone());
+        job.message = Some(message.clone());
+        self.state.status = Some(message.clone());
+        if let Err(err) = history::append(&HistoryEntry::of(job)) {
+            self.state.status = Some(format!("Failed to write the job history: {err}"));
+        }
+        let retries = self.config.max_retries;
+        if let Some(failure) = job.failure.filter(|f| status == JobStatus::Failed && f.is_transient()) {
+            if job.attempts <= retries {
+                // The run failed past ffmpeg's check for an existing output, what is there is partial.
+                remove_partial_outputs(&outputs);
+                job.status = JobStatus::Pending;
+                job.progress = RemuxProgress::default();
+                job.message = Some(format!("{}, retry {} of {retries}", failure.describe(), job.attempts));
+                self.state.status = job.message.clone();
+            }
+        } else if let Some(fix) = job.fix() {
+            self.state.status = Some(format!("{message}, F to {} and retry the failed jobs", fix.describe()));
+        }
+        if replaced && self.state.selected_file.as_deref().map(Path::new) == Some(input.as_path()) {
+            self.state.probe = probe::probe(&input).ok();
+            self.state.faststart = mp4::is_faststart(&input).ok().flatten();
+        }
+        self.save_jobs();
+        self.start_jobs();
     }
 
     pub fn render(&mut self, f: &mut Frame<'_>) -> Result<(), Box<dyn std::error::Error>> {
@@ -92,13 +1485,153 @@ impl App {
         ])
         .split(chunks[0]);
 
+        let bottom_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(vec![
+            Constraint::Length(3),
+            Constraint::Min(0),
+        ])
+        .split(chunks[1]);
+
         self.file_browser.render(f,inner_layout[0], true, &self.state);
         f.render_widget(
             Paragraph::new(self.state.selected_file.clone().unwrap_or_else(|| "No file selected".to_string()))
-                .block(Block::new().borders(Borders::ALL).style(ratatui::style::Style::default().bg(APP_BACKGROUND))),
+                .block(Block::new().borders(Borders::ALL).style(ratatui::style::Style::default().bg(APP_BACKGROUND))
+                    .title_bottom(self.state.status.clone().unwrap_or_default())),
                 
-            chunks[1]);
+            bottom_layout[0]);
+        let remux_layout = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![
+            Constraint::Percentage(60),
+            Constraint::Percentage(40),
+        ])
+        .split(bottom_layout[1]);
+
         self.video_info.render(f, inner_layout[1], false, &self.state);       
+        self.remux_progress.render(f, remux_layout[0], false, &self.state);
+        self.jobs_view.render(f, remux_layout[1], self.focus.current() == 3, &self.state);
+        if let Some(dry_run) = &self.state.dry_run {
+            render_dry_run(f, f.area(), dry_run, &self.theme);
+        }
+        if let Some(batch) = &self.state.batch {
+            render_batch(f, f.area(), batch, &self.theme);
+        }
+        if let Some(history) = &self.state.history {
+            render_history(f, f.area(), history, &self.theme);
+        }
+        if let Some(input) = &self.state.text_input {
+            render_text_input(f, input, &self.state, &self.config, &self.theme);
+        }
+        if let Some(prompt) = &self.state.prompt {
+            render_prompt(f, f.area(), prompt, &self.theme);
+        }
         Ok(())
     }
+}
+
+fn render_text_input(f: &mut Frame, input: &TextInput, state: &AppState, config: &Config, theme: &Theme) {
+    let details = match input.purpose {
+        InputPurpose::AudioTranscode => vec![
+            Line::from("Codec (ac3, eac3, aac, flac), then optional bitrate (640k), channels (2ch, 5.1) and keep"),
+            Line::from("keep adds the new track after the original instead of replacing it; empty to copy again."),
+        ],
+        InputPurpose::TrackEdit => vec![
+            Line::from("language=fre; title=Director's commentary; default=yes; forced=no (leave out what should not change)"),
+            Line::from("Matroska headers are edited in place when there is room, other files are remuxed in place."),
+        ],
+        InputPurpose::Split => vec![
+            Line::from("chapters, a duration (30m, 1h) or a size (700MB, 4G); empty to write a single file"),
+            Line::from("Parts are cut on keyframes, so sizes and durations are approximate."),
+        ],
+        InputPurpose::BatchOutput => vec![Line::from("Full path of the output of this file")],
+        InputPurpose::BatchStreams => vec![
+            Line::from("Indexes of the input streams to keep, e.g. 0, 1, 3; the others are dropped"),
+        ],
+        InputPurpose::Scan => vec![
+            Line::from("include=*S01*, Season 2/**; exclude=*sample*, extras; ext=mkv, mp4; min=100MB (left out parts match all)"),
+            Line::from("Scans the folder under the cursor recursively, probes every match and marks it for the batch (b)."),
+        ],
+        InputPurpose::TrackRules => vec![
+            Line::from("keep video; keep audio where language in [eng, fre] and not commentary; keep subs where forced or lang = eng"),
+            Line::from("default audio where language = eng; drop streams where codec = mjpeg (separated by ;, empty to keep all)"),
+        ],
+        InputPurpose::TrimRanges => vec![
+            Line::from("Ranges to keep, e.g. 1:30-45:00, 1:00:00- (empty to keep everything)"),
+            Line::from("Starts are moved back to the previous keyframe so the video can be copied."),
+        ],
+        InputPurpose::OutputTemplate => {
+            let preview = match (&state.selected_file, &state.probe) {
+                (Some(file), Some(probe)) => {
+                    match template::render(&input.value, Path::new(file), probe, &config.container) {
+                        Ok(path) => format!("Preview: {}", path.display()),
+                        Err(err) => format!("Error: {err}"),
+                    }
+                }
+                _ => "Preview: select a video file".to_string(),
+            };
+            let placeholders = template::PLACEHOLDERS
+                .iter()
+                .map(|p| format!("{{{p}}}"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            vec![Line::from(preview), Line::from(format!("Placeholders: {placeholders}"))]
+        }
+    };
+    input.render(f, f.area(), details, theme);
+}
+
+/// Writes the files a job reads besides its inputs, once its output still fits next to
+/// what running jobs hold. Returns the space the job holds in turn.
+fn prepare_job(job: &Job, config: &Config, reserved: &Reserved) -> Result<Vec<Claim>, String> {
+    // Space may have gone to the jobs that ran since this one was queued.
+    let plan = match &job.kind {
+        JobKind::Remux(plan) => plan,
+        JobKind::Extract(plan) => {
+            // Without a probe the sizes are unknown, the directories are still checked.
+            let probe = probe::probe(&plan.input).unwrap_or_default();
+            let checked = preflight::check_extract(plan, &probe, reserved).map_err(|err| format!("Blocked: {err}"))?;
+            return Ok(checked.claims);
+        }
+    };
+    let backup_dir = backup_dir(plan, config);
+    let checked =
+        preflight::check(plan, backup_dir.as_deref(), reserved).map_err(|err| format!("Blocked: {err}"))?;
+    for (path, content) in plan.support_files() {
+        fs::write(&path, content).map_err(|err| format!("Failed to write {}: {err}", path.display()))?;
+    }
+    Ok(checked.claims)
+}
+
+/// Where the original of an in-place remux is backed up to.
+fn backup_dir(plan: &RemuxPlan, config: &Config) -> Option<PathBuf> {
+    in_place::backup_dir(&plan.input, config.backup, Path::new(&config.backup_dir))
+}
+
+/// Swaps the verified temporary output of an in-place remux with the original.
+fn finish_in_place(plan: &RemuxPlan, verdict: &Verdict, config: &Config) -> Result<String, String> {
+    if !verdict.passed() {
+        remove_partial_outputs(std::slice::from_ref(&plan.output));
+        return Err(format!("{} left untouched, {}", plan.input.display(), verdict.describe()));
+    }
+    match in_place::replace(&plan.input, &plan.output, config.backup, Path::new(&config.backup_dir)) {
+        Ok(Some(backup)) => Ok(format!("Replaced {}, original kept at {}", plan.input.display(), backup.display())),
+        Ok(None) => Ok(format!("Replaced {}", plan.input.display())),
+        Err(err) => Err(format!(
+            "Could not replace {}: {err} (new file left at {})",
+            plan.input.display(),
+            plan.output.display()
+        )),
+    }
+}
+
+/// Concat lists and chapter metadata written for the job by `prepare_job`.
+fn remove_support_files(job: &Job) {
+    for (path, _) in job.plan().map(RemuxPlan::support_files).unwrap_or_default() {
+        fs::remove_file(path).ok();
+    }
+}
+
+fn display_paths(paths: &[PathBuf]) -> String {
+    paths.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join(", ")
 }
\ No newline at end of file
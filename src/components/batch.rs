@@ -0,0 +1,201 @@
+use std::path::{Path, PathBuf};
+
+use ratatui::{
+    layout::{Constraint, Flex, Layout, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table, TableState, Wrap},
+    Frame,
+};
+
+use crate::{
+    remux::{
+        conflict::{self, Conflict, ConflictPolicy},
+        plan::RemuxPlan,
+        preflight,
+    },
+    theme::theme::Theme,
+    units::human_bytes,
+};
+
+/// One file of a batch about to be queued.
+#[derive(Debug, Clone)]
+pub struct BatchRow {
+    pub input: PathBuf,
+    /// Why the file could not be planned, in which case it is never queued.
+    pub plan: Result<RemuxPlan, String>,
+    pub included: bool,
+    /// Output after applying the conflict policy.
+    output: Option<PathBuf>,
+    conflict: Option<Result<Conflict, String>>,
+    /// The output exists and the policy replaces it.
+    overwrites: bool,
+    estimate: Option<u64>,
+}
+
+impl BatchRow {
+    pub fn new(input: PathBuf, plan: Result<RemuxPlan, String>, default_policy: ConflictPolicy) -> Self {
+        let mut row =
+            BatchRow { input, plan, included: false, output: None, conflict: None, overwrites: false, estimate: None };
+        row.refresh(default_policy);
+        row.included = row.queueable();
+        row
+    }
+
+    /// Recomputes what depends on the plan, after it was edited.
+    pub fn refresh(&mut self, default_policy: ConflictPolicy) {
+        let Ok(plan) = &self.plan else {
+            return;
+        };
+        let mut resolved = plan.clone();
+        let conflict = conflict::apply(&mut resolved, plan.conflict_policy.unwrap_or(default_policy));
+        self.overwrites = resolved.overwrite && !resolved.in_place && resolved.first_output_file().exists();
+        self.output = Some(resolved.written_output());
+        self.conflict = Some(conflict);
+        self.estimate = preflight::estimated_size(plan);
+        self.included &= self.queueable();
+    }
+
+    /// Whether running the plan would write anything.
+    pub fn queueable(&self) -> bool {
+        self.plan.is_ok() && matches!(self.conflict, Some(Ok(Conflict::None)))
+    }
+
+    fn streams(&self) -> String {
+        match &self.plan {
+            Ok(plan) => {
+                let kept = plan.output_streams().len() + plan.muxed_sidecars().len();
+                match plan.dropped.len() {
+                    0 => format!("{kept} kept"),
+                    dropped => format!("{kept} kept, {dropped} dropped"),
+                }
+            }
+            Err(_) => String::new(),
+        }
+    }
+
+    fn notes(&self) -> Vec<String> {
+        let plan = match &self.plan {
+            Ok(plan) => plan,
+            Err(err) => return vec![err.clone()],
+        };
+        let mut notes = plan.warnings.clone();
+        match &self.conflict {
+            Some(Ok(Conflict::Skip | Conflict::Ask)) => notes.insert(0, "output exists, skipped".to_string()),
+            Some(Err(err)) => notes.insert(0, err.clone()),
+            _ if self.overwrites => notes.insert(0, "output exists, overwritten".to_string()),
+            _ => {}
+        }
+        notes
+    }
+}
+
+/// Plans of a batch shown as a table before they are queued.
+#[derive(Debug, Clone)]
+pub struct BatchPreview {
+    pub rows: Vec<BatchRow>,
+    pub cursor: usize,
+}
+
+impl BatchPreview {
+    pub fn new(rows: Vec<BatchRow>) -> Self {
+        Self { rows, cursor: 0 }
+    }
+
+    pub fn current(&mut self) -> Option<&mut BatchRow> {
+        self.rows.get_mut(self.cursor)
+    }
+
+    pub fn move_cursor(&mut self, delta: isize) {
+        let last = self.rows.len().saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(delta).min(last);
+    }
+
+    pub fn toggle(&mut self) {
+        if let Some(row) = self.current() {
+            row.included = !row.included && row.queueable();
+        }
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+pub fn render_batch(f: &mut Frame, area: Rect, batch: &BatchPreview, theme: &Theme) {
+    let [area] = Layout::horizontal([Constraint::Percentage(90)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [area] = Layout::vertical([Constraint::Percentage(80)])
+        .flex(Flex::Center)
+        .areas(area);
+
+    let included = batch.rows.iter().filter(|r| r.included).count();
+    let total: u64 = batch.rows.iter().filter(|r| r.included).filter_map(|r| r.estimate).sum();
+    let block = Block::new()
+        .title(Line::raw(format!("Batch: {included} of {} file(s), about {}", batch.rows.len(), human_bytes(total as f64))).centered())
+        .title_bottom(
+            Line::raw("[Space] include/exclude  [o] output  [k] kept streams  [Enter] queue  [Esc] close").centered(),
+        )
+        .borders(Borders::ALL)
+        .bg(theme.background);
+    let inner = block.inner(area);
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+
+    let [table_area, details_area] = Layout::vertical([Constraint::Min(3), Constraint::Length(6)]).areas(inner);
+
+    let rows: Vec<Row> = batch
+        .rows
+        .iter()
+        .map(|row| {
+            let notes = row.notes();
+            let note = match notes.as_slice() {
+                [] => String::new(),
+                [one] => one.clone(),
+                [first, rest @ ..] => format!("{first} (+{})", rest.len()),
+            };
+            let cells = vec![
+                Cell::from(if row.included { "[x]" } else { "[ ]" }),
+                Cell::from(file_name(&row.input)),
+                Cell::from(row.output.as_deref().map(file_name).unwrap_or_default()),
+                Cell::from(row.streams()),
+                Cell::from(row.estimate.map(|e| human_bytes(e as f64)).unwrap_or_else(|| "?".to_string())),
+                Cell::from(note),
+            ];
+            let table_row = Row::new(cells);
+            match (&row.plan, notes.is_empty()) {
+                (Err(_), _) => table_row.red(),
+                (Ok(_), false) => table_row.yellow(),
+                (Ok(_), true) => table_row,
+            }
+        })
+        .collect();
+    let widths = [
+        Constraint::Length(3),
+        Constraint::Percentage(25),
+        Constraint::Percentage(25),
+        Constraint::Length(20),
+        Constraint::Length(10),
+        Constraint::Fill(1),
+    ];
+    let table = Table::new(rows, widths)
+        .header(Row::new(["", "Input", "Output", "Streams", "Size", "Warnings"]).bold())
+        .row_highlight_style(theme.highlight);
+    let mut state = TableState::default().with_selected(Some(batch.cursor));
+    f.render_stateful_widget(table, table_area, &mut state);
+
+    let mut details = vec![];
+    if let Some(row) = batch.rows.get(batch.cursor) {
+        details.push(Line::from(format!("Input:  {}", row.input.display())));
+        if let Some(output) = &row.output {
+            details.push(Line::from(format!("Output: {}", output.display())));
+        }
+        if let Ok(plan) = &row.plan {
+            let kept: Vec<String> = plan.output_streams().iter().map(|s| s.source.to_string()).collect();
+            details.push(Line::from(format!("Kept streams: {}", kept.join(", "))));
+        }
+        details.extend(row.notes().into_iter().map(|n| Line::from(format!("Warning: {n}")).red()));
+    }
+    f.render_widget(Paragraph::new(details).wrap(Wrap { trim: false }), details_area);
+}
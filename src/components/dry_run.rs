@@ -0,0 +1,148 @@
+use std::path::{Path, PathBuf};
+
+use ratatui::{
+    layout::{Constraint, Flex, Layout, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::{
+    config::Config,
+    ffmpeg::{command_line, runner},
+    mp4,
+    remux::{
+        conflict::{self, Conflict, ConflictPolicy},
+        in_place,
+        plan::RemuxPlan,
+        preflight::{self, Preflight, Reserved},
+    },
+    theme::theme::Theme,
+};
+
+/// A planned remux shown with its exact command line before anything runs.
+#[derive(Debug, Clone)]
+pub struct DryRun {
+    pub plan: RemuxPlan,
+    pub policy: ConflictPolicy,
+    pub conflict: Result<Conflict, String>,
+    /// Output after applying the policy, e.g. with an auto-suffix.
+    pub output: PathBuf,
+    /// Where `s` saves the command, next to the output.
+    pub script: PathBuf,
+    /// Files written before running, such as the concat list of a trim.
+    pub files: Vec<(PathBuf, String)>,
+    pub argv: Vec<String>,
+    /// Space and permission check, an error blocking the run.
+    pub preflight: Result<Preflight, String>,
+}
+
+impl DryRun {
+    /// Resolves the output conflict on a copy of the plan, the way running it would.
+    pub fn new(plan: RemuxPlan, config: &Config, reserved: &Reserved) -> Self {
+        let policy = plan.conflict_policy.unwrap_or(config.conflict_policy);
+        let mut resolved = plan.clone();
+        let conflict = conflict::apply(&mut resolved, policy);
+        let argv = runner::command_line(&resolved.ffmpeg_args());
+        let files = resolved.support_files();
+        let backup_dir = in_place::backup_dir(&resolved.input, config.backup, Path::new(&config.backup_dir));
+        let preflight = preflight::check(&resolved, backup_dir.as_deref(), reserved);
+        let script = resolved.output.with_extension("sh");
+        Self { plan, policy, conflict, output: resolved.written_output(), script, files, argv, preflight }
+    }
+
+    pub fn shell_command(&self) -> String {
+        command_line::to_shell(&self.argv)
+    }
+}
+
+pub fn render_dry_run(f: &mut Frame, area: Rect, dry_run: &DryRun, theme: &Theme) {
+    let [area] = Layout::horizontal([Constraint::Percentage(80)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [area] = Layout::vertical([Constraint::Percentage(50)])
+        .flex(Flex::Center)
+        .areas(area);
+
+    let block = Block::new()
+        .title(Line::raw("Dry run").centered())
+        .title_bottom(
+            Line::raw("[Enter] run  [y] copy  [s] save script  [p] conflict policy  [Esc] close").centered(),
+        )
+        .borders(Borders::ALL)
+        .bg(theme.background);
+
+    let conflict = match &dry_run.conflict {
+        Ok(Conflict::None) => Line::from(format!("If output exists: {}", dry_run.policy.label())),
+        Ok(Conflict::Skip) => Line::from("Output exists: job will be skipped").yellow(),
+        Ok(Conflict::Ask) => Line::from("Output exists: you will be asked before overwriting").yellow(),
+        Err(err) => Line::from(format!("Refused: {err}")).red(),
+    };
+    let mut lines = vec![];
+    for warning in &dry_run.plan.warnings {
+        lines.push(Line::from(format!("Warning: {warning}")).red());
+    }
+    if dry_run.plan.segments.iter().any(|s| s.path != dry_run.plan.input) {
+        for segment in &dry_run.plan.segments {
+            lines.push(Line::from(format!("Part:   {}", segment.path.display())));
+        }
+    } else {
+        lines.push(Line::from(format!("Input:  {}", dry_run.plan.input.display())));
+    }
+    for index in &dry_run.plan.dropped {
+        if let Some(stream) = dry_run.plan.source.streams.iter().find(|s| s.index == *index) {
+            let language = stream.language().unwrap_or("und");
+            lines.push(Line::from(format!("Drop:   stream {index} {} {} {language}", stream.codec_type(), stream.codec_name())));
+        }
+    }
+    for transcode in &dry_run.plan.audio {
+        let codec = dry_run
+            .plan
+            .source
+            .streams
+            .iter()
+            .find(|s| s.index == transcode.stream)
+            .map(|s| s.codec_name())
+            .unwrap_or("?");
+        lines.push(Line::from(format!("Audio:  stream {} {codec} -> {}", transcode.stream, transcode.describe())));
+    }
+    if let Some(format) = dry_run.plan.subtitles {
+        lines.push(Line::from(format!("Subs:   text subtitles converted to {}", format.label())));
+    }
+    for sidecar in &dry_run.plan.sidecars {
+        lines.push(Line::from(format!("   +    {}", sidecar.describe())));
+    }
+    if dry_run.plan.faststart && mp4::is_mp4_container(&dry_run.plan.container()) {
+        lines.push(Line::from("Faststart: index moved to the front for streaming"));
+    }
+    if dry_run.plan.in_place {
+        lines.push(Line::from("In place: the input is replaced once the output is verified").yellow());
+    }
+    lines.extend([
+        Line::from(format!("Output: {}", dry_run.output.display())),
+        conflict,
+        match &dry_run.preflight {
+            Ok(preflight) => Line::from(format!("Space:  {}", preflight.describe())),
+            Err(err) => Line::from(format!("Blocked: {err}")).red(),
+        },
+    ]);
+    if let Some(split) = &dry_run.plan.split {
+        lines.push(Line::from(format!("Split:  {}", split.describe())));
+    }
+    for (path, content) in &dry_run.files {
+        lines.push(Line::from(""));
+        lines.push(Line::from(format!("{}:", path.display())));
+        lines.extend(content.lines().map(|l| Line::from(format!("  {l}"))));
+    }
+    lines.extend([
+        Line::from(""),
+        Line::from(dry_run.shell_command()).fg(theme.directory),
+    ]);
+    let paragraph = Paragraph::new(lines)
+    .wrap(Wrap { trim: false })
+    .block(block);
+
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
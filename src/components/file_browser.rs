@@ -30,12 +30,12 @@ pub struct FileBrowser {
 }
 
 trait Extension {
-    fn is_hidden(self) -> bool;
+    fn is_hidden(&self) -> bool;
 }
 
-impl Extension for &PathBuf {
+impl Extension for PathBuf {
     #[cfg(unix)]
-    fn is_hidden(self) -> bool {
+    fn is_hidden(&self) -> bool {
         self.file_name()
             .unwrap()
             .as_bytes()[0] == b'.'
@@ -44,7 +44,6 @@ impl Extension for &PathBuf {
 
 impl FocusableWidget for FileBrowser {
     fn render(&mut self, f: &mut Frame, area: Rect, focused: bool, app_state: &AppState) {
-        let _ = app_state;
         let _ = focused;
 
         let block = Block::new()
@@ -56,10 +55,11 @@ impl FocusableWidget for FileBrowser {
         let items: Vec<ListItem> = self
         .entries
         .iter()
-        .enumerate()
-        .map(|(_i, item)| {
+        .map(|item| {
             let line = if item.is_dir {
                 Line::styled(item.filename.clone(), self.theme.directory)
+            } else if app_state.marked_files.contains(&self.current_dir.join(&item.filename)) {
+                Line::styled(format!("* {}", item.filename), self.theme.marked)
             } else {
                 Line::styled(item.filename.clone(), self.theme.text)
             };
@@ -85,6 +85,7 @@ impl FocusableWidget for FileBrowser {
                 KeyCode::Down => self.move_selection(1),
                 KeyCode::Enter => self.enter_dir(),
                 KeyCode::Left => self.go_back(),
+                KeyCode::Char(' ') => self.toggle_mark(app_state),
                 _ => {}
             }
             app_state.selected_file = self.state.selected().map(|i| {
@@ -177,6 +178,18 @@ impl FileBrowser {
         }
     }
 
+    fn toggle_mark(&mut self, app_state: &mut AppState) {
+        if let Some(entry) = self.state.selected().map(|i| &self.entries[i]) {
+            if entry.is_dir {
+                return;
+            }
+            let path = self.current_dir.join(&entry.filename);
+            if !app_state.marked_files.remove(&path) {
+                app_state.marked_files.insert(path);
+            }
+        }
+    }
+
     fn go_back(&mut self) { 
         if self.current_dir.pop() {
             self.entries = Self::read_dir(&self.current_dir);
@@ -0,0 +1,123 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Flex, Layout, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Borders, Clear, HighlightSpacing, List, ListItem, ListState, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::{
+    jobs::{
+        history::{self, HistoryEntry},
+        job::JobStatus,
+    },
+    theme::theme::Theme,
+    units::human_duration,
+};
+
+/// Past jobs, newest first, narrowed by a filter typed in the popup.
+#[derive(Debug, Clone)]
+pub struct HistoryView {
+    entries: Vec<HistoryEntry>,
+    pub filter: String,
+    cursor: usize,
+}
+
+impl HistoryView {
+    pub fn load() -> Self {
+        Self { entries: history::load(), filter: String::new(), cursor: 0 }
+    }
+
+    fn filtered(&self) -> Vec<&HistoryEntry> {
+        self.entries.iter().filter(|e| e.matches(&self.filter)).collect()
+    }
+
+    /// Returns false once the view is closed.
+    pub fn handle_key(&mut self, key: &KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Esc => return false,
+            KeyCode::Up => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Down => self.cursor = (self.cursor + 1).min(self.filtered().len().saturating_sub(1)),
+            KeyCode::Backspace => {
+                self.filter.pop();
+                self.cursor = 0;
+            }
+            KeyCode::Char(c) => {
+                self.filter.push(c);
+                self.cursor = 0;
+            }
+            _ => {}
+        }
+        true
+    }
+}
+
+pub fn render_history(f: &mut Frame, area: Rect, view: &HistoryView, theme: &Theme) {
+    let [area] = Layout::horizontal([Constraint::Percentage(90)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [area] = Layout::vertical([Constraint::Percentage(80)])
+        .flex(Flex::Center)
+        .areas(area);
+
+    let entries = view.filtered();
+    let block = Block::new()
+        .title(Line::raw(format!("History ({} of {} jobs)", entries.len(), view.entries.len())).centered())
+        .title_bottom(Line::raw("Type to filter  [Up/Down] select  [Esc] close").centered())
+        .borders(Borders::ALL)
+        .bg(theme.background);
+    let inner = block.inner(area);
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+
+    let [filter_area, list_area, details_area] =
+        Layout::vertical([Constraint::Length(1), Constraint::Percentage(50), Constraint::Fill(1)]).areas(inner);
+    f.render_widget(Paragraph::new(format!("Filter: {}", view.filter)).fg(theme.directory), filter_area);
+
+    let items: Vec<ListItem> = entries
+        .iter()
+        .map(|entry| {
+            let line = Line::from(format!(
+                "{}  {:>9}  {}",
+                entry.when(),
+                entry.status.label(),
+                entry.input.display()
+            ));
+            ListItem::new(match entry.status {
+                JobStatus::Done => line.green(),
+                JobStatus::Failed => line.red(),
+                JobStatus::Cancelled => line.yellow(),
+                JobStatus::Pending | JobStatus::Running => line,
+            })
+        })
+        .collect();
+    let list = List::new(items)
+        .highlight_style(theme.highlight)
+        .highlight_symbol(">")
+        .highlight_spacing(HighlightSpacing::Always);
+    let mut state = ListState::default().with_selected((!entries.is_empty()).then_some(view.cursor));
+    f.render_stateful_widget(list, list_area, &mut state);
+
+    let Some(entry) = entries.get(view.cursor) else {
+        return;
+    };
+    let mut lines = vec![
+        Line::from(format!(
+            "Outputs: {}",
+            entry.outputs.iter().map(|o| o.display().to_string()).collect::<Vec<_>>().join(", ")
+        )),
+        Line::from(format!(
+            "Ran for {}, exit code {}{}",
+            human_duration(std::time::Duration::from_secs_f64(entry.duration)),
+            entry.exit_code.map(|c| c.to_string()).unwrap_or_else(|| "?".to_string()),
+            entry.verification.as_ref().map(|v| format!(", {v}")).unwrap_or_default()
+        )),
+    ];
+    if let Some(message) = &entry.message {
+        lines.push(Line::from(message.clone()));
+    }
+    lines.push(Line::from(entry.command.clone()).fg(theme.directory));
+    lines.extend(entry.stderr_tail.iter().map(|l| Line::from(l.clone()).red()));
+    f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), details_area);
+}
@@ -0,0 +1,103 @@
+use ratatui::{
+    layout::Rect,
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Borders, HighlightSpacing, List, ListItem, ListState},
+    Frame,
+};
+use crossterm::event::KeyCode;
+
+use crate::{
+    events::events::AppEvent,
+    jobs::job::{Job, JobStatus},
+    state::AppState,
+    theme::theme::Theme,
+};
+
+use super::component_base::FocusableWidget;
+
+/// The batch: every queued job with its status.
+pub struct JobsView {
+    theme: Theme,
+    focused: bool,
+}
+
+impl FocusableWidget for JobsView {
+    fn render(&mut self, f: &mut Frame, area: Rect, focused: bool, app_state: &AppState) {
+        let queue = &app_state.jobs;
+        let mut title = format!("Jobs ({} pending)", queue.pending());
+        if queue.held {
+            title.push_str(" - held, Enter to start");
+        }
+        if let Some(scan) = app_state.scan.as_ref().filter(|s| s.running) {
+            title.push_str(&format!(" - scanning: {}", scan.describe()));
+        }
+        let mut block = Block::new()
+            .title(Line::raw(title).centered())
+            .borders(Borders::ALL)
+            .bg(self.theme.background);
+        if focused {
+            block = block.title_bottom("[Enter] start/retry  [Del] cancel/remove");
+        }
+
+        let items: Vec<ListItem> = queue.jobs.iter().map(|job| ListItem::new(job_line(job))).collect();
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(self.theme.highlight)
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+
+        let mut state = ListState::default();
+        if focused && !queue.jobs.is_empty() {
+            state.select(Some(app_state.job_cursor.min(queue.jobs.len() - 1)));
+        }
+        f.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn handle_event(&mut self, event: &AppEvent, app_state: &mut AppState) {
+        if let AppEvent::Input(key) = event {
+            let last = app_state.jobs.jobs.len().saturating_sub(1);
+            match key.code {
+                KeyCode::Up => app_state.job_cursor = app_state.job_cursor.min(last).saturating_sub(1),
+                KeyCode::Down => app_state.job_cursor = (app_state.job_cursor + 1).min(last),
+                _ => {}
+            }
+        }
+    }
+
+    fn has_focus(&self) -> bool {
+        self.focused
+    }
+
+    fn set_focus(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+}
+
+fn job_line(job: &Job) -> Line<'static> {
+    let name = job.input().file_name().unwrap_or_default().to_string_lossy().into_owned();
+    let status = match (job.status, job.ratio()) {
+        (JobStatus::Running, Some(ratio)) => format!("{:>5.1}%", ratio * 100.0),
+        (status, _) => status.label().to_string(),
+    };
+    let mut text = format!("{status:>9}  {name}");
+    if let Some(message) = &job.message {
+        text.push_str(&format!(" - {message}"));
+    }
+    let line = Line::from(text);
+    match job.status {
+        JobStatus::Done => line.green(),
+        JobStatus::Failed => line.red(),
+        JobStatus::Cancelled => line.yellow(),
+        JobStatus::Pending | JobStatus::Running => line,
+    }
+}
+
+impl JobsView {
+    pub fn new() -> Self {
+        JobsView {
+            theme: Theme::default(),
+            focused: false,
+        }
+    }
+}
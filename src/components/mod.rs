@@ -1,3 +1,10 @@
 pub mod file_browser;
 pub mod component_base;
 pub mod video_info;
+pub mod remux_progress;
+pub mod jobs;
+pub mod prompt;
+pub mod dry_run;
+pub mod batch;
+pub mod history;
+pub mod text_input;
@@ -0,0 +1,57 @@
+use ratatui::{
+    layout::{Constraint, Flex, Layout, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::{
+    remux::{extract::ExtractPlan, plan::RemuxPlan},
+    theme::theme::Theme,
+};
+
+/// Yes/no question shown on top of the other panes.
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    pub message: String,
+    pub action: PromptAction,
+}
+
+#[derive(Debug, Clone)]
+pub enum PromptAction {
+    CancelRemuxAndQuit,
+    /// Pending jobs were found at startup; yes starts them, no keeps them held.
+    ResumeBatch,
+    /// Some failed jobs have a known fix; yes applies it and queues them again.
+    ApplyFixes,
+    /// Output exists and the conflict policy is `ask`; yes overwrites, no skips.
+    Overwrite(Box<RemuxPlan>),
+    OverwriteExtract(Box<ExtractPlan>),
+    /// The script of the open dry run exists.
+    OverwriteScript,
+}
+
+pub fn render_prompt(f: &mut Frame, area: Rect, prompt: &Prompt, theme: &Theme) {
+    let [area] = Layout::horizontal([Constraint::Percentage(50)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [area] = Layout::vertical([Constraint::Length(6)])
+        .flex(Flex::Center)
+        .areas(area);
+
+    let block = Block::new()
+        .title(Line::raw("Confirm").centered())
+        .borders(Borders::ALL)
+        .bg(theme.background);
+    let paragraph = Paragraph::new(vec![
+        Line::from(prompt.message.clone()),
+        Line::from(""),
+        Line::from("[y] Yes   [n] No").centered(),
+    ])
+    .wrap(Wrap { trim: true })
+    .block(block);
+
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
@@ -0,0 +1,183 @@
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Borders, Gauge, Paragraph},
+    Frame,
+};
+
+use crate::{
+    events::events::AppEvent,
+    jobs::job::JobStatus,
+    state::AppState,
+    theme::theme::Theme,
+    units::{human_bytes, human_duration},
+};
+
+use super::component_base::FocusableWidget;
+
+pub struct RemuxProgressView {
+    theme: Theme,
+    focused: bool,
+}
+
+impl FocusableWidget for RemuxProgressView {
+    fn render(&mut self, f: &mut Frame, area: Rect, focused: bool, app_state: &AppState) {
+        let _ = focused;
+
+        let block = Block::new()
+            .title(Line::raw("Remux").centered())
+            .borders(Borders::ALL)
+            .bg(self.theme.background);
+
+        let Some(job) = app_state.current_job.and_then(|id| app_state.jobs.get(id)) else {
+            let mut lines = vec![Line::from(
+                "No remux running. Press r to remux the selected file, d for a dry run, c to cancel, k to trim, s to split, t to edit the output template, o to change the conflict policy.",
+            )];
+            match &app_state.output_preview {
+                _ if app_state.in_place => lines.push(Line::from("Output: replaces the input (i to change)").yellow()),
+                Some(Ok(output)) => lines.push(Line::from(format!("Output: {}", output.display()))),
+                Some(Err(err)) => lines.push(Line::from(format!("Output template error: {err}")).red()),
+                None => {}
+            }
+            if !app_state.trim.is_empty() {
+                lines.push(Line::from("Keep (k to edit):"));
+                for range in &app_state.trim {
+                    let line = Line::from(format!("    {}", range.describe()));
+                    lines.push(if range.offset() > 0.001 { line.yellow() } else { line });
+                }
+            }
+            if let Some(format) = app_state.subtitles {
+                lines.push(Line::from(format!("Subtitles: converted to {} (v to change)", format.label())));
+            }
+            if !app_state.rules.is_empty() {
+                lines.push(Line::from(format!("Track rules: {} (l to edit)", app_state.rules.len())));
+            }
+            if let Some(split) = &app_state.split {
+                lines.push(Line::from(format!("Split: {} (s to change)", split.describe())));
+            }
+            if !app_state.sidecars.is_empty() {
+                let mark = if app_state.mux_sidecars { "x" } else { " " };
+                lines.push(Line::from(format!(
+                    "[{mark}] Mux {} sidecar file(s) (a to toggle):",
+                    app_state.sidecars.len()
+                )));
+                for sidecar in &app_state.sidecars {
+                    lines.push(Line::from(format!("    {}", sidecar.describe())));
+                }
+            }
+            let paragraph = Paragraph::new(lines).block(block);
+            f.render_widget(paragraph, area);
+            return;
+        };
+
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![Constraint::Length(1), Constraint::Length(1), Constraint::Min(0)])
+            .split(inner);
+
+        let elapsed = job.started.map(|s| s.elapsed()).unwrap_or_default();
+        let progress = &job.progress;
+        let duration = job.duration();
+        let ratio = match &job.outcome {
+            Some(outcome) if outcome.success => Some(1.0),
+            _ => progress.ratio(duration),
+        };
+
+        f.render_widget(
+            Paragraph::new(format!(
+                "{} -> {}",
+                job.input().display(),
+                job.outputs().iter().map(|o| o.display().to_string()).collect::<Vec<_>>().join(", ")
+            )),
+            rows[0],
+        );
+
+        let gauge = Gauge::default()
+            .gauge_style(self.theme.highlight)
+            .ratio(ratio.unwrap_or(0.0))
+            .label(match ratio {
+                Some(ratio) => format!("{:.1}%", ratio * 100.0),
+                None => "?".to_string(),
+            });
+        f.render_widget(gauge, rows[1]);
+
+        let total = duration
+            .map(|d| human_duration(std::time::Duration::from_secs_f64(d)))
+            .unwrap_or_else(|| "?".to_string());
+        let mut lines = vec![
+            Line::from(format!(
+                "Time: {} / {}   Speed: {}   Frames: {}",
+                human_duration(progress.out_time),
+                total,
+                progress.speed.map(|s| format!("{s:.2}x")).unwrap_or_else(|| "N/A".to_string()),
+                progress.frame,
+            )),
+            Line::from(format!(
+                "Written: {}   Throughput: {}/s",
+                human_bytes(progress.total_size as f64),
+                human_bytes(progress.throughput(elapsed).unwrap_or(0.0)),
+            )),
+        ];
+
+        match &job.outcome {
+            None if job.status == JobStatus::Pending => lines.push(Line::from("Waiting in the queue")),
+            None => lines.push(Line::from(format!(
+                "Elapsed: {}   ETA: {}",
+                human_duration(elapsed),
+                progress
+                    .eta(duration, elapsed)
+                    .map(human_duration)
+                    .unwrap_or_else(|| "?".to_string()),
+            ))),
+            Some(outcome) if outcome.cancelled => lines.push(Line::from("Cancelled").yellow()),
+            Some(outcome) if outcome.success => match &job.verdict {
+                Some(verdict) if !verdict.passed() => {
+                    lines.push(Line::from("Done, but the output does not match the plan:").red());
+                    lines.extend(verdict.problems.iter().map(|p| Line::from(format!("    {p}"))));
+                }
+                Some(_) => lines.push(Line::from("Done, output verified").green()),
+                None => lines.push(Line::from("Done").green()),
+            },
+            Some(outcome) => {
+                lines.push(
+                    Line::from(format!(
+                        "Failed (exit code {})",
+                        outcome.exit_code.map(|c| c.to_string()).unwrap_or_else(|| "?".to_string())
+                    ))
+                    .red(),
+                );
+                if let Some(last) = outcome.stderr_tail.last() {
+                    lines.push(Line::from(last.clone()));
+                }
+            }
+        }
+
+        f.render_widget(Paragraph::new(lines), rows[2]);
+    }
+
+    fn handle_event(&mut self, event: &AppEvent, app_state: &mut AppState) {
+        let _ = event;
+        let _ = app_state;
+    }
+
+    fn has_focus(&self) -> bool {
+        self.focused
+    }
+
+    fn set_focus(&mut self, _focused: bool) {
+        self.focused = _focused;
+    }
+}
+
+impl RemuxProgressView {
+    pub fn new() -> Self {
+        RemuxProgressView {
+            theme: Theme::default(),
+            focused: false,
+        }
+    }
+}
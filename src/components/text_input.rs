@@ -0,0 +1,104 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Flex, Layout, Position, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::theme::theme::Theme;
+
+/// Single line editor shown as a popup.
+#[derive(Debug, Clone)]
+pub struct TextInput {
+    pub title: String,
+    pub value: String,
+    pub purpose: InputPurpose,
+    cursor: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputPurpose {
+    OutputTemplate,
+    TrimRanges,
+    Split,
+    AudioTranscode,
+    TrackEdit,
+    TrackRules,
+    Scan,
+    BatchOutput,
+    BatchStreams,
+}
+
+pub enum InputResult {
+    Editing,
+    Submitted(String),
+    Cancelled,
+}
+
+impl TextInput {
+    pub fn new(title: &str, value: String, purpose: InputPurpose) -> Self {
+        let cursor = value.chars().count();
+        Self { title: title.to_string(), value, purpose, cursor }
+    }
+
+    pub fn handle_key(&mut self, key: &KeyEvent) -> InputResult {
+        match key.code {
+            KeyCode::Enter => return InputResult::Submitted(self.value.clone()),
+            KeyCode::Esc => return InputResult::Cancelled,
+            KeyCode::Char(c) => {
+                let at = self.byte_index();
+                self.value.insert(at, c);
+                self.cursor += 1;
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                let at = self.byte_index();
+                self.value.remove(at);
+            }
+            KeyCode::Delete if self.cursor < self.value.chars().count() => {
+                let at = self.byte_index();
+                self.value.remove(at);
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.value.chars().count()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.value.chars().count(),
+            _ => {}
+        }
+        InputResult::Editing
+    }
+
+    fn byte_index(&self) -> usize {
+        self.value
+            .char_indices()
+            .nth(self.cursor)
+            .map(|(i, _)| i)
+            .unwrap_or(self.value.len())
+    }
+
+    /// Renders the editor with extra lines (help, preview...) below the value.
+    pub fn render(&self, f: &mut Frame, area: Rect, details: Vec<Line>, theme: &Theme) {
+        let [area] = Layout::horizontal([Constraint::Percentage(80)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [area] = Layout::vertical([Constraint::Length(details.len() as u16 + 4)])
+            .flex(Flex::Center)
+            .areas(area);
+
+        let block = Block::new()
+            .title(Line::raw(self.title.clone()).centered())
+            .title_bottom(Line::raw("[Enter] apply  [Esc] cancel").centered())
+            .borders(Borders::ALL)
+            .bg(theme.background);
+        let inner = block.inner(area);
+
+        let mut lines = vec![Line::from(self.value.clone()).fg(theme.directory), Line::from("")];
+        lines.extend(details);
+
+        f.render_widget(Clear, area);
+        f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }).block(block), area);
+        f.set_cursor_position(Position::new(inner.x + self.cursor as u16, inner.y));
+    }
+}
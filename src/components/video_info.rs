@@ -1,17 +1,13 @@
-use std::process::Command;
-
 use crossterm::event::KeyCode;
-use ratatui::{layout::Rect, text::Line, widgets::{Block, Borders, ListState, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState}, Frame};
-use serde_json::Value;
+use ratatui::{layout::Rect, style::{Style, Stylize}, text::Line, widgets::{Block, Borders, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState}, Frame};
 
-use crate::{events::events::AppEvent, state::AppState, theme::theme::Theme};
+use crate::{events::events::AppEvent, ffmpeg::probe::StreamInfo, remux::rules, state::AppState, theme::theme::Theme};
 
 use super::component_base::FocusableWidget;
 
 pub struct VideoInfo {
     vertical_scroll_state: ScrollbarState,
     vertical_scroll: usize,
-    state: ListState,
     theme: Theme,
     focused: bool,
 }
@@ -21,84 +17,58 @@ impl FocusableWidget for VideoInfo {
     fn render(&mut self, f: &mut Frame, area: Rect, focused: bool, app_state: &AppState) {
         let _ = focused;
 
-        let filename_owned = app_state.selected_file.clone().unwrap_or_else(|| "No file selected".to_string());
-        let filename = filename_owned.as_str(); 
-        let is_video: bool = is_video_with_ffprobe(filename);
-        if  is_video == false {
-        
-            let paragraph = Paragraph::new(format!("value: {}",is_video.to_string()))
+        let Some(probe) = &app_state.probe else {
+            let paragraph = Paragraph::new("No video information")
             .block(Block::new().borders(Borders::ALL).style(ratatui::style::Style::default().bg(self.theme.background)));
             f.render_widget(paragraph,area);
             return;
-        }
+        };
 
-
-        let output = Command::new("ffprobe")
-                                    .args([ "-print_format","json",
-                                            "-show_format",
-                                            "-show_streams",
-                                            &filename,
-                                            ])
-                                            .output()
-                                            .expect("Failed to execute ffprobe");                                            
-        if !output.status.success() {
-            return;
-        }
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let result: Value = serde_json::from_str(&stdout).unwrap_or_else(|_| {
-            eprintln!("Failed to parse ffprobe output");
-            Value::Null
-        });
-
-        let binding = vec![];
-        let streams = result["streams"].as_array().unwrap_or(&binding);
         let mut label = vec![];
+        let mut cursor_line = 0;
+        match app_state.faststart {
+            Some(true) => label.extend([Line::from("Faststart: yes"), Line::from("")]),
+            Some(false) => label.extend([Line::from("Faststart: no, index at the end (f to optimize)").yellow(), Line::from("")]),
+            None => {}
+        }
 
-        for stream in streams {
-            if let Some(index) = stream.get("index") {
-                label.push(Line::from(format!("Stream Index: {}\n", index.as_u64().unwrap_or(0))));
-            }
-            if let Some(codec_type) = stream.get("codec_type") {
-                label.push(Line::from(format!("Codec Type: {}\n", codec_type.as_str().unwrap_or("Unknown"))));
-            }               
-
-            if let Some(codec_name) = stream.get("codec_name") {
-                label.push(Line::from(format!("Codec Name: {}\n", codec_name.as_str().unwrap_or("Unknown"))));
+        let dropped = rules::dropped(&app_state.rules, &probe.streams);
+        for (i, stream) in probe.streams.iter().enumerate() {
+            let is_cursor = i == app_state.stream_cursor;
+            if is_cursor {
+                cursor_line = label.len();
             }
-            if let Some(codec_name) = stream.get("codec_long_name") {
-                label.push(Line::from(format!("Long Codec Name: {}\n", codec_name.as_str().unwrap_or("Unknown"))));
+            let mark = if app_state.selected_streams.contains(&stream.index) { "[x]" } else { "[ ]" };
+            let style = if is_cursor { self.theme.highlight } else { Style::default() };
+            for (n, line) in stream_lines(stream).into_iter().enumerate() {
+                let line = if n == 0 { format!("{mark} {line}") } else { format!("    {line}") };
+                label.push(Line::styled(line, style));
             }
-         
-            if let Some(width) = stream.get("width") {
-                label.push(Line::from(format!("Width: {}\n", width.as_u64().unwrap_or(0))));
+            if let Some(transcode) = app_state.audio.iter().find(|t| t.stream == stream.index) {
+                label.push(Line::styled(format!("    Transcode to: {}", transcode.describe()), style.fg(self.theme.marked)));
             }
-            if let Some(height) = stream.get("height") {
-                label.push(Line::from(format!("Height: {}\n", height.as_u64().unwrap_or(0))));
+            if dropped.contains(&stream.index) {
+                label.push(Line::styled("    Dropped by track rules", style.red()));
             }
-
-            if let Some(channel_layout) = stream.get("channel_layout") {
-                label.push(Line::from(format!("Channel Layout: {}\n", channel_layout.as_str().unwrap_or("Unknown"))));
-            }
-
-            let tags = stream.get("tags").unwrap_or(&Value::Null);
-            if let Some(language) = tags.get("language") {
-                if !language.as_str().unwrap_or("").is_empty() {
-                    label.push(Line::from(format!("Language: {}\n", language.as_str().unwrap_or("Unknown"))));
-                }
-            }
-            if let Some(title) = tags.get("title") {
-                if !title.as_str().unwrap_or("").is_empty() {
-                    label.push(Line::from(format!("Title: {}\n", title.as_str().unwrap_or("Unknown"))));
-                }
-            };
-
             label.push(Line::from(""));
         }
 
-        self.vertical_scroll_state = self.vertical_scroll_state.content_length(label.len());
+        // Keep the stream under the cursor visible.
+        let height = area.height.saturating_sub(2) as usize;
+        if cursor_line < self.vertical_scroll {
+            self.vertical_scroll = cursor_line;
+        } else if height > 0 && cursor_line >= self.vertical_scroll + height {
+            self.vertical_scroll = cursor_line + 1 - height;
+        }
+
+        self.vertical_scroll_state = self
+            .vertical_scroll_state
+            .content_length(label.len())
+            .position(self.vertical_scroll);
 
         let paragraph = Paragraph::new(label)
         .block(Block::new().borders(Borders::ALL)
+        .title_bottom(Line::raw("[Space] select  [x] extract  [e] transcode  [h] edit").centered())
         .style(ratatui::style::Style::default().bg(self.theme.background)))
         .scroll((self.vertical_scroll as u16, 0));
 
@@ -112,22 +82,28 @@ impl FocusableWidget for VideoInfo {
         );
 
     }
-    
+
     fn handle_event(&mut self, event: &AppEvent, app_state: &mut AppState) {
-        let _ = app_state;
         if !self.has_focus() { return; }
 
+        let stream_count = app_state.probe.as_ref().map(|p| p.streams.len()).unwrap_or(0);
+        if stream_count == 0 { return; }
+
         if let AppEvent::Input(key) = event {
             match key.code {
                 KeyCode::Down => {
-                    self.vertical_scroll = self.vertical_scroll.saturating_add(3);
-                    self.vertical_scroll_state =
-                        self.vertical_scroll_state.position(self.vertical_scroll);
+                    app_state.stream_cursor = (app_state.stream_cursor + 1).min(stream_count - 1);
                 }
                 KeyCode::Up => {
-                    self.vertical_scroll = self.vertical_scroll.saturating_sub(3);
-                    self.vertical_scroll_state =
-                        self.vertical_scroll_state.position(self.vertical_scroll);
+                    app_state.stream_cursor = app_state.stream_cursor.saturating_sub(1);
+                }
+                KeyCode::Char(' ') => {
+                    if let Some(stream) = app_state.probe.as_ref().and_then(|p| p.streams.get(app_state.stream_cursor)) {
+                        let index = stream.index;
+                        if !app_state.selected_streams.remove(&index) {
+                            app_state.selected_streams.insert(index);
+                        }
+                    }
                 }
 
                 _ => {}
@@ -148,32 +124,37 @@ impl FocusableWidget for VideoInfo {
 impl VideoInfo {
     pub fn new() -> Self {
         VideoInfo {
-            state: ListState::default(),
             theme: Theme::default(),
             focused: false,
             vertical_scroll_state: ScrollbarState::default(),
             vertical_scroll: 0,
         }
-    } 
+    }
 }
 
-fn is_video_with_ffprobe(path: &str) -> bool {
-
-    let output = Command::new("ffprobe")
-        .args([
-            "-v", "error",
-            "-select_streams", "v", // video streams only
-            "-show_entries", "stream=codec_type",
-            "-of", "default=noprint_wrappers=1:nokey=1",
-            path, // <-- no "-i"!
-        ])
-        .output()
-        .expect("Failed to execute ffprobe");
-
-    if !output.status.success() {
-        return false;
+fn stream_lines(stream: &StreamInfo) -> Vec<String> {
+    let mut lines = vec![format!("Stream Index: {}", stream.index)];
+    lines.push(format!("Codec Type: {}", stream.codec_type.as_deref().unwrap_or("Unknown")));
+    if let Some(codec_name) = &stream.codec_name {
+        lines.push(format!("Codec Name: {codec_name}"));
     }
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    stdout.contains("video")
-}
\ No newline at end of file
+    if let Some(codec_name) = &stream.codec_long_name {
+        lines.push(format!("Long Codec Name: {codec_name}"));
+    }
+    if let Some(width) = stream.width {
+        lines.push(format!("Width: {width}"));
+    }
+    if let Some(height) = stream.height {
+        lines.push(format!("Height: {height}"));
+    }
+    if let Some(channel_layout) = &stream.channel_layout {
+        lines.push(format!("Channel Layout: {channel_layout}"));
+    }
+    if let Some(language) = stream.language() {
+        lines.push(format!("Language: {language}"));
+    }
+    if let Some(title) = stream.title() {
+        lines.push(format!("Title: {title}"));
+    }
+    lines
+}
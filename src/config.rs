@@ -0,0 +1,84 @@
+use std::{
+    env, fs, io,
+    path::PathBuf,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    remux::{conflict::ConflictPolicy, in_place::Backup},
+    scan::ScanFilter,
+};
+
+/// User settings, stored as JSON in `$XDG_CONFIG_HOME/fmuxie/config.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub output_template: String,
+    pub container: String,
+    pub conflict_policy: ConflictPolicy,
+    /// Names of extracted streams; also accepts `{index}`, `{lang}`, `{codec}` and `{type}`.
+    pub extract_template: String,
+    /// Add a chapter at the start of every joined part.
+    pub concat_chapters: bool,
+    /// Move the index of MP4 outputs to the front so they can be streamed.
+    pub faststart: bool,
+    /// What happens to the original after an in-place remux.
+    pub backup: Backup,
+    /// Where originals go when `backup` is `folder`.
+    pub backup_dir: String,
+    /// Jobs of the queue running at the same time.
+    pub max_jobs: usize,
+    /// Never run two jobs reading or writing the same disk at once, seeks would slow both down.
+    pub one_job_per_disk: bool,
+    /// Times a job killed or stopped by an I/O error is run again before it counts as failed.
+    pub max_retries: u32,
+    /// Stream selection applied to every remux, one rule per entry, see `remux::rules`.
+    pub track_rules: Vec<String>,
+    /// Files a folder scan adds to the batch.
+    pub scan: ScanFilter,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            output_template: "{dir}/{stem}.remux.{container}".to_string(),
+            container: "mkv".to_string(),
+            conflict_policy: ConflictPolicy::default(),
+            extract_template: "{dir}/{stem}.track{index}.{lang}.{ext}".to_string(),
+            concat_chapters: true,
+            faststart: true,
+            backup: Backup::default(),
+            backup_dir: String::new(),
+            max_jobs: 2,
+            one_job_per_disk: true,
+            max_retries: 2,
+            track_rules: vec![],
+            scan: ScanFilter::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> Self {
+        config_dir()
+            .map(|dir| dir.join("config.json"))
+            .and_then(|path| fs::read(path).ok())
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let dir = config_dir().ok_or_else(|| io::Error::other("no config directory"))?;
+        fs::create_dir_all(&dir)?;
+        let json = serde_json::to_vec_pretty(self).map_err(io::Error::other)?;
+        fs::write(dir.join("config.json"), json)
+    }
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("fmuxie"))
+}
@@ -17,10 +17,10 @@ impl EventQueue {
         let input_tx = tx.clone();
         thread::spawn(move || {
             loop {
-                if event::poll(Duration::from_millis(100)).unwrap() {
-                    if let CEvent::Key(key) = event::read().unwrap() {
-                        input_tx.send(AppEvent::Input(key)).unwrap();
-                    }
+                if event::poll(Duration::from_millis(100)).unwrap()
+                    && let CEvent::Key(key) = event::read().unwrap()
+                {
+                    input_tx.send(AppEvent::Input(key)).unwrap();
                 }
                 input_tx.send(AppEvent::Tick).ok();
             }
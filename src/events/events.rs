@@ -1,9 +1,17 @@
 // events.rs
 use crossterm::event::KeyEvent;
 
+use crate::{
+    ffmpeg::runner::RemuxEvent,
+    jobs::job::JobId,
+    scan::{ScanEvent, ScanId},
+};
+
 #[derive(Debug, Clone)]
 pub enum AppEvent {
     Input(KeyEvent),
     Tick,
     Custom(String),
-}
\ No newline at end of file
+    Remux(JobId, RemuxEvent),
+    Scan(ScanId, ScanEvent),
+}
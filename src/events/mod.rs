@@ -1,2 +1,3 @@
+#[allow(clippy::module_inception)]
 pub mod events;
 pub mod event_queue;
\ No newline at end of file
@@ -0,0 +1,88 @@
+use std::{
+    fs, io,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+
+/// Quotes an argument for a POSIX shell, leaving plain words untouched.
+pub fn quote(arg: &str) -> String {
+    let is_plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=+,@%".contains(c));
+    if is_plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+pub fn to_shell(argv: &[String]) -> String {
+    argv.iter().map(|arg| quote(arg)).collect::<Vec<_>>().join(" ")
+}
+
+/// Writes the commands to an executable script that stops at the first failure.
+/// `files` are recreated by the script before the commands run, with a final newline.
+pub fn save_script(path: &Path, files: &[(PathBuf, String)], commands: &[Vec<String>]) -> io::Result<()> {
+    let mut script = String::from("#!/bin/sh\nset -e\n\n");
+    for (file, content) in files {
+        let newline = if content.ends_with('\n') || content.is_empty() { "" } else { "\n" };
+        let delimiter = heredoc_delimiter(content);
+        let file = quote(&file.to_string_lossy());
+        script.push_str(&format!("cat > {file} <<'{delimiter}'\n{content}{newline}{delimiter}\n\n"));
+    }
+    for argv in commands {
+        script.push_str(&to_shell(argv));
+        script.push('\n');
+    }
+    fs::write(path, script)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+}
+
+/// End marker of a here-document that no line of `content` is equal to.
+fn heredoc_delimiter(content: &str) -> String {
+    (0..)
+        .map(|n| if n == 0 { "FMUXIE_EOF".to_string() } else { format!("FMUXIE_EOF_{n}") })
+        .find(|delimiter| !content.lines().any(|line| line == delimiter))
+        .expect("unbounded range")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    #[test]
+    fn quotes_only_what_the_shell_would_read() {
+        assert_eq!(quote("-c:a"), "-c:a");
+        assert_eq!(quote("/media/in/Movie.2019.mkv"), "/media/in/Movie.2019.mkv");
+        assert_eq!(quote(""), "''");
+        assert_eq!(quote("My Movie.mkv"), "'My Movie.mkv'");
+        assert_eq!(quote("Director's Cut"), r"'Director'\''s Cut'");
+        assert_eq!(quote("$HOME`id`"), "'$HOME`id`'");
+        assert_eq!(quote("title=A; rm -rf ~"), "'title=A; rm -rf ~'");
+        assert_eq!(to_shell(&["ffmpeg".to_string(), "-i".to_string(), "a b".to_string()]), "ffmpeg -i 'a b'");
+    }
+
+    #[test]
+    fn scripts_recreate_their_files() {
+        let dir = env::temp_dir();
+        let id = process::id();
+        let files = [
+            (dir.join(format!("fmuxie-{id}-script list.ffconcat")), "ffconcat version 1.0\nfile 'a.mkv'".to_string()),
+            (dir.join(format!("fmuxie-{id}-script.ffmeta")), ";FFMETADATA1\nFMUXIE_EOF\ntitle=$HOME\n".to_string()),
+        ];
+        let script = dir.join(format!("fmuxie-{id}-script.sh"));
+        let echo = vec!["echo".to_string(), "it's done".to_string()];
+        save_script(&script, &files, &[echo]).unwrap();
+        let output = process::Command::new("sh").arg(&script).output().unwrap();
+        let written: Vec<String> = files.iter().map(|(path, _)| fs::read_to_string(path).unwrap()).collect();
+        fs::remove_file(&script).ok();
+        for (path, _) in &files {
+            fs::remove_file(path).ok();
+        }
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "it's done\n");
+        assert_eq!(written, [format!("{}\n", files[0].1), files[1].1.clone()]);
+    }
+}
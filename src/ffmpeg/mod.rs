@@ -0,0 +1,4 @@
+pub mod command_line;
+pub mod probe;
+pub mod progress;
+pub mod runner;
@@ -0,0 +1,200 @@
+use std::{collections::HashMap, io, path::Path, process::Command};
+
+use serde::{Deserialize, Serialize};
+
+/// Result of `ffprobe -show_format -show_streams -show_chapters`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProbeInfo {
+    #[serde(default)]
+    pub streams: Vec<StreamInfo>,
+    #[serde(default)]
+    pub format: FormatInfo,
+    #[serde(default)]
+    pub chapters: Vec<ChapterInfo>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FormatInfo {
+    #[serde(default)]
+    pub filename: String,
+    #[serde(default)]
+    pub format_name: String,
+    pub duration: Option<String>,
+    pub size: Option<String>,
+    pub bit_rate: Option<String>,
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StreamInfo {
+    pub index: usize,
+    pub codec_type: Option<String>,
+    pub codec_name: Option<String>,
+    pub codec_long_name: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub sample_rate: Option<String>,
+    pub channels: Option<u32>,
+    pub channel_layout: Option<String>,
+    pub bit_rate: Option<String>,
+    pub duration: Option<String>,
+    #[serde(default)]
+    pub disposition: Disposition,
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Disposition {
+    #[serde(default)]
+    pub default: u8,
+    #[serde(default)]
+    pub forced: u8,
+    #[serde(default)]
+    pub comment: u8,
+    #[serde(default)]
+    pub hearing_impaired: u8,
+    #[serde(default)]
+    pub attached_pic: u8,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChapterInfo {
+    pub id: i64,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+}
+
+impl ProbeInfo {
+    pub fn duration(&self) -> Option<f64> {
+        self.format.duration.as_deref().and_then(|d| d.parse().ok())
+    }
+
+    /// Whether both probes describe the same file: same size, duration and streams. Any
+    /// remux of a file changes its size, header edits aside.
+    pub fn matches(&self, other: &ProbeInfo) -> bool {
+        let streams = |probe: &ProbeInfo| -> Vec<(usize, String, String)> {
+            probe.streams.iter().map(|s| (s.index, s.codec_type().to_string(), s.codec_name().to_string())).collect()
+        };
+        self.format.size == other.format.size
+            && self.format.duration == other.format.duration
+            && streams(self) == streams(other)
+    }
+
+    /// Streams whose codec_type is `video`, `audio`, `subtitle`, ...
+    pub fn streams_of<'a>(&'a self, codec_type: &'a str) -> impl Iterator<Item = &'a StreamInfo> {
+        self.streams.iter().filter(move |s| s.codec_type() == codec_type)
+    }
+}
+
+impl StreamInfo {
+    pub fn codec_type(&self) -> &str {
+        self.codec_type.as_deref().unwrap_or("unknown")
+    }
+
+    pub fn codec_name(&self) -> &str {
+        self.codec_name.as_deref().unwrap_or("unknown")
+    }
+
+    /// Language tag, ignoring the `und` placeholder.
+    pub fn language(&self) -> Option<&str> {
+        self.tags
+            .get("language")
+            .map(String::as_str)
+            .filter(|l| !l.is_empty() && *l != "und")
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.tags.get("title").map(String::as_str).filter(|t| !t.is_empty())
+    }
+}
+
+pub fn probe(path: &Path) -> io::Result<ProbeInfo> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v", "error",
+            "-print_format", "json",
+            "-show_format",
+            "-show_streams",
+            "-show_chapters",
+        ])
+        .arg(path)
+        .output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(stderr.trim().to_string()));
+    }
+
+    serde_json::from_slice(&output.stdout).map_err(io::Error::other)
+}
+
+/// Timestamps of the video keyframes around the given times, read from the packet flags
+/// so nothing has to be decoded.
+pub fn keyframes_near(path: &Path, times: &[f64]) -> io::Result<Vec<f64>> {
+    let intervals = times
+        .iter()
+        .map(|t| format!("{:.3}%{:.3}", (t - 30.0).max(0.0), t + 1.0))
+        .collect::<Vec<_>>()
+        .join(",");
+    let output = Command::new("ffprobe")
+        .args([
+            "-v", "error",
+            "-select_streams", "v:0",
+            "-read_intervals", &intervals,
+            "-show_entries", "packet=pts_time,flags",
+            "-of", "csv=p=0",
+        ])
+        .arg(path)
+        .output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(stderr.trim().to_string()));
+    }
+
+    let mut keyframes: Vec<f64> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once(','))
+        .filter(|(_, flags)| flags.contains('K'))
+        .filter_map(|(pts, _)| pts.parse().ok())
+        .collect();
+    keyframes.sort_by(f64::total_cmp);
+    keyframes.dedup();
+    Ok(keyframes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROBE: &str = r#"{
+        "streams": [
+            {"index": 0, "codec_type": "video", "codec_name": "h264"},
+            {"index": 1, "codec_type": "audio", "codec_name": "dts", "tags": {"language": "eng"}},
+            {"index": 2, "codec_type": "subtitle", "codec_name": "subrip", "tags": {"language": "und"}}
+        ],
+        "format": {"format_name": "matroska,webm", "duration": "5400.021000", "size": "8423551270"}
+    }"#;
+
+    #[test]
+    fn matches_only_the_same_file() {
+        let stored: ProbeInfo = serde_json::from_str(PROBE).unwrap();
+        assert!(stored.matches(&stored.clone()));
+        assert_eq!(stored.duration(), Some(5400.021));
+        assert_eq!(stored.streams[2].language(), None);
+        assert_eq!(stored.streams_of("audio").count(), 1);
+
+        // The remux that replaced the file dropped the subtitles.
+        let mut replaced = stored.clone();
+        replaced.streams.pop();
+        replaced.format.size = Some("8401220118".to_string());
+        assert!(!stored.matches(&replaced));
+        let mut resized = stored.clone();
+        resized.format.size = Some("8423551271".to_string());
+        assert!(!stored.matches(&resized));
+    }
+}
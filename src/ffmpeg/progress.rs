@@ -0,0 +1,168 @@
+use std::time::Duration;
+
+/// Snapshot of one `-progress` block written by ffmpeg.
+#[derive(Debug, Clone, Default)]
+pub struct RemuxProgress {
+    pub out_time: Duration,
+    pub speed: Option<f64>,
+    pub total_size: u64,
+    pub frame: u64,
+    pub finished: bool,
+}
+
+impl RemuxProgress {
+    /// Fraction of the input already written, given the probed duration in seconds.
+    pub fn ratio(&self, duration: Option<f64>) -> Option<f64> {
+        let duration = duration.filter(|d| *d > 0.0)?;
+        Some((self.out_time.as_secs_f64() / duration).clamp(0.0, 1.0))
+    }
+
+    /// Remaining wall-clock time, using ffmpeg's own speed factor when it reports one
+    /// and falling back to the elapsed time otherwise.
+    pub fn eta(&self, duration: Option<f64>, elapsed: Duration) -> Option<Duration> {
+        let duration = duration.filter(|d| *d > 0.0)?;
+        let remaining = (duration - self.out_time.as_secs_f64()).max(0.0);
+        let secs = match self.speed.filter(|s| *s > 0.0) {
+            Some(speed) => remaining / speed,
+            None => {
+                let ratio = self.ratio(Some(duration))?;
+                if ratio <= 0.0 {
+                    return None;
+                }
+                elapsed.as_secs_f64() * (1.0 - ratio) / ratio
+            }
+        };
+        Some(Duration::from_secs_f64(secs))
+    }
+
+    /// Bytes written per second of wall-clock time.
+    pub fn throughput(&self, elapsed: Duration) -> Option<f64> {
+        let secs = elapsed.as_secs_f64();
+        (secs > 0.0).then(|| self.total_size as f64 / secs)
+    }
+}
+
+/// Accumulates the `key=value` lines of `ffmpeg -progress` output; a block ends with a
+/// `progress=continue` or `progress=end` line.
+#[derive(Debug, Default)]
+pub struct ProgressParser {
+    current: RemuxProgress,
+}
+
+impl ProgressParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn feed(&mut self, line: &str) -> Option<RemuxProgress> {
+        let (key, value) = line.trim().split_once('=')?;
+        let value = value.trim();
+        match key {
+            "frame" => self.current.frame = value.parse().unwrap_or(self.current.frame),
+            "total_size" => self.current.total_size = value.parse().unwrap_or(self.current.total_size),
+            // Despite the name, out_time_ms is in microseconds too.
+            "out_time_us" | "out_time_ms" => {
+                if let Ok(us) = value.parse::<i64>() {
+                    self.current.out_time = Duration::from_micros(us.max(0) as u64);
+                }
+            }
+            "out_time" => {
+                if let Some(time) = parse_timestamp(value) {
+                    self.current.out_time = time;
+                }
+            }
+            "speed" => self.current.speed = value.trim_end_matches('x').trim().parse().ok(),
+            "progress" => {
+                self.current.finished = value == "end";
+                return Some(self.current.clone());
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Parses `HH:MM:SS.micro` timestamps as printed by ffmpeg.
+pub fn parse_timestamp(value: &str) -> Option<Duration> {
+    let mut parts = value.trim().splitn(3, ':');
+    let hours: u64 = parts.next()?.parse().ok()?;
+    let minutes: u64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    if seconds < 0.0 {
+        return None;
+    }
+    Some(Duration::from_secs(hours * 3600 + minutes * 60) + Duration::from_secs_f64(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_progress_block() {
+        let block = "frame=2400\nfps=480.12\nbitrate=4012.3kbits/s\ntotal_size=50331648\nout_time_us=100100000\n\
+                     out_time_ms=100100000\nout_time=00:01:40.100000\ndup_frames=0\ndrop_frames=0\nspeed=20.1x\n";
+        let mut parser = ProgressParser::new();
+        assert!(block.lines().all(|line| parser.feed(line).is_none()));
+        let progress = parser.feed("progress=continue").unwrap();
+        assert_eq!(progress.frame, 2400);
+        assert_eq!(progress.total_size, 50_331_648);
+        assert_eq!(progress.out_time, Duration::from_millis(100_100));
+        assert_eq!(progress.speed, Some(20.1));
+        assert!(!progress.finished);
+        assert!(parser.feed("progress=end").unwrap().finished);
+    }
+
+    #[test]
+    fn keeps_values_ffmpeg_reports_as_na() {
+        let mut parser = ProgressParser::new();
+        for line in ["total_size=1024", "out_time_us=2000000", "progress=continue"] {
+            parser.feed(line);
+        }
+        // Audio-only outputs report N/A frames and, at the start, N/A speed and times.
+        for line in ["frame=N/A", "total_size=N/A", "out_time_us=N/A", "out_time=N/A", "speed=N/A"] {
+            assert!(parser.feed(line).is_none());
+        }
+        let progress = parser.feed("progress=continue").unwrap();
+        assert_eq!(progress.total_size, 1024);
+        assert_eq!(progress.out_time, Duration::from_secs(2));
+        assert_eq!(progress.speed, None);
+        assert!(parser.feed("not a progress line").is_none());
+    }
+
+    #[test]
+    fn clamps_negative_start_times() {
+        let mut parser = ProgressParser::new();
+        parser.feed("out_time_us=-23220");
+        assert_eq!(parser.feed("progress=continue").unwrap().out_time, Duration::ZERO);
+    }
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(parse_timestamp("01:02:03.500000"), Some(Duration::from_millis(3_723_500)));
+        assert_eq!(parse_timestamp("00:00:00"), Some(Duration::ZERO));
+        assert_eq!(parse_timestamp("-00:00:01.000000"), None);
+        assert_eq!(parse_timestamp("1:30"), None);
+    }
+
+    #[test]
+    fn estimates_ratio_and_eta() {
+        let progress = RemuxProgress { out_time: Duration::from_secs(30), speed: Some(10.0), ..Default::default() };
+        assert_eq!(progress.ratio(Some(120.0)), Some(0.25));
+        assert_eq!(progress.ratio(None), None);
+        assert_eq!(progress.eta(Some(120.0), Duration::from_secs(3)), Some(Duration::from_secs(9)));
+
+        let no_speed = RemuxProgress { speed: None, ..progress.clone() };
+        assert_eq!(no_speed.eta(Some(120.0), Duration::from_secs(4)), Some(Duration::from_secs(12)));
+        let later = RemuxProgress { out_time: Duration::from_secs(200), ..progress };
+        assert_eq!(later.ratio(Some(120.0)), Some(1.0));
+        assert_eq!(later.eta(Some(120.0), Duration::from_secs(4)), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn measures_throughput() {
+        let progress = RemuxProgress { total_size: 10_000_000, ..Default::default() };
+        assert_eq!(progress.throughput(Duration::from_secs(4)), Some(2_500_000.0));
+        assert_eq!(progress.throughput(Duration::ZERO), None);
+    }
+}
@@ -0,0 +1,162 @@
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{events::events::AppEvent, jobs::job::JobId};
+
+use super::progress::{ProgressParser, RemuxProgress};
+
+const STDERR_TAIL_LINES: usize = 20;
+/// How long ffmpeg gets to finalize the output after being asked to quit.
+const CANCEL_TIMEOUT: Duration = Duration::from_secs(5);
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone)]
+pub enum RemuxEvent {
+    Progress(RemuxProgress),
+    Finished(RemuxOutcome),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemuxOutcome {
+    pub success: bool,
+    pub cancelled: bool,
+    pub exit_code: Option<i32>,
+    pub stderr_tail: Vec<String>,
+}
+
+/// Handle on a running ffmpeg process, used to cancel it.
+pub struct RemuxHandle {
+    child: Arc<Mutex<Child>>,
+    stdin: Option<ChildStdin>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl RemuxHandle {
+    /// Asks ffmpeg to stop and kills it if it is still running after the timeout.
+    pub fn cancel(&mut self) {
+        self.request_quit();
+        let child = Arc::clone(&self.child);
+        thread::spawn(move || wait_or_kill(&child, CANCEL_TIMEOUT));
+    }
+
+    /// Same as [`RemuxHandle::cancel`] but blocks until the process is gone.
+    pub fn cancel_and_wait(&mut self) {
+        self.request_quit();
+        wait_or_kill(&self.child, CANCEL_TIMEOUT);
+    }
+
+    fn request_quit(&mut self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        if let Some(mut stdin) = self.stdin.take() {
+            stdin.write_all(b"q").ok();
+        }
+    }
+}
+
+/// Full ffmpeg argv for the given arguments, as executed by [`spawn`].
+pub fn command_line(args: &[String]) -> Vec<String> {
+    ["ffmpeg", "-hide_banner", "-loglevel", "warning", "-nostats", "-progress", "pipe:1"]
+        .into_iter()
+        .map(String::from)
+        .chain(args.iter().cloned())
+        .collect()
+}
+
+/// Starts ffmpeg with machine readable progress on stdout and reports it on the event queue,
+/// tagged with `job`, until the process exits.
+pub fn spawn(job: JobId, args: Vec<String>, sender: Sender<AppEvent>) -> io::Result<RemuxHandle> {
+    let argv = command_line(&args);
+    let mut child = Command::new(&argv[0])
+        .args(&argv[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let stdin = child.stdin.take();
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let child = Arc::new(Mutex::new(child));
+    let cancelled = Arc::new(AtomicBool::new(false));
+
+    let stderr_reader = thread::spawn(move || {
+        let mut tail = VecDeque::with_capacity(STDERR_TAIL_LINES);
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            if tail.len() == STDERR_TAIL_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(line);
+        }
+        Vec::from(tail)
+    });
+
+    let handle = RemuxHandle {
+        child: Arc::clone(&child),
+        stdin,
+        cancelled: Arc::clone(&cancelled),
+    };
+
+    thread::spawn(move || {
+        let mut parser = ProgressParser::new();
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if let Some(progress) = parser.feed(&line) {
+                sender.send(AppEvent::Remux(job, RemuxEvent::Progress(progress))).ok();
+            }
+        }
+
+        let stderr_tail = stderr_reader.join().unwrap_or_default();
+        let status = wait(&child);
+        if let Err(err) = &status {
+            sender.send(AppEvent::Custom(format!("Failed to wait for ffmpeg: {err}"))).ok();
+        }
+        let outcome = RemuxOutcome {
+            success: status.as_ref().is_ok_and(|s| s.success()),
+            cancelled: cancelled.load(Ordering::SeqCst),
+            exit_code: status.ok().and_then(|s| s.code()),
+            stderr_tail,
+        };
+        sender.send(AppEvent::Remux(job, RemuxEvent::Finished(outcome))).ok();
+    });
+
+    Ok(handle)
+}
+
+// Polls instead of blocking in `Child::wait` so the lock stays available to `cancel`.
+fn wait(child: &Mutex<Child>) -> io::Result<ExitStatus> {
+    loop {
+        if let Some(status) = child.lock().unwrap().try_wait()? {
+            return Ok(status);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn wait_or_kill(child: &Mutex<Child>, timeout: Duration) {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let mut child = child.lock().unwrap();
+        match child.try_wait() {
+            Ok(None) if Instant::now() >= deadline => {
+                child.kill().ok();
+                child.wait().ok();
+                return;
+            }
+            Ok(None) => {}
+            _ => return,
+        }
+        drop(child);
+        thread::sleep(POLL_INTERVAL);
+    }
+}
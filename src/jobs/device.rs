@@ -0,0 +1,212 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    os::unix::fs::{FileTypeExt, MetadataExt},
+    path::{Path, PathBuf},
+};
+
+/// Device-mapper and md devices stacked deeper than this are taken as they are.
+const MAX_DEPTH: usize = 8;
+
+/// Names of the disks holding `path`, like `sda` or `nvme0n1`. Partitions count as their
+/// disk; LVM, LUKS and md devices as the disks under them; btrfs as the disks of its pool.
+/// Filesystems without a block device (NFS, tmpfs) are named after their device number.
+/// `path` may not exist yet, its closest existing ancestor is used.
+pub fn disks_of(path: &Path) -> BTreeSet<String> {
+    let sys = Path::new("/sys");
+    let Some((existing, metadata)) = path.ancestors().find_map(|p| fs::metadata(p).ok().map(|m| (p, m))) else {
+        return BTreeSet::new();
+    };
+    let (major, minor) = (libc::major(metadata.dev()), libc::minor(metadata.dev()));
+    if let Some(block) = block_name(sys, major, minor) {
+        return physical_disks(sys, &block, 0);
+    }
+    // btrfs gives every subvolume an anonymous device number, its mount names the device.
+    let source = fs::read_to_string("/proc/self/mountinfo")
+        .ok()
+        .zip(fs::canonicalize(existing).ok())
+        .and_then(|(mountinfo, path)| mount_source(&parse_mountinfo(&mountinfo), &path));
+    let block = source
+        .and_then(|(fs_type, source)| {
+            let device = fs::metadata(&source).ok().filter(|m| m.file_type().is_block_device())?;
+            let block = block_name(sys, libc::major(device.rdev()), libc::minor(device.rdev()))?;
+            Some(if fs_type == "btrfs" { btrfs_pool(sys, &block) } else { BTreeSet::from([block]) })
+        })
+        .filter(|blocks| !blocks.is_empty());
+    match block {
+        Some(blocks) => blocks.iter().flat_map(|block| physical_disks(sys, block, 0)).collect(),
+        None => BTreeSet::from([format!("{major}:{minor}")]),
+    }
+}
+
+/// Kernel name of the block device with the given numbers, `None` when it is not one.
+fn block_name(sys: &Path, major: u32, minor: u32) -> Option<String> {
+    let block = fs::canonicalize(sys.join(format!("dev/block/{major}:{minor}"))).ok()?;
+    block.file_name().map(|name| name.to_string_lossy().into_owned())
+}
+
+/// Disks at the bottom of the stack of block devices under `block`, through the `slaves`
+/// of device-mapper and md devices and the disk of partitions.
+fn physical_disks(sys: &Path, block: &str, depth: usize) -> BTreeSet<String> {
+    let Ok(dir) = fs::canonicalize(sys.join("class/block").join(block)) else {
+        return BTreeSet::from([block.to_string()]);
+    };
+    if depth < MAX_DEPTH {
+        let slaves = entry_names(&dir.join("slaves"));
+        if !slaves.is_empty() {
+            return slaves.iter().flat_map(|slave| physical_disks(sys, slave, depth + 1)).collect();
+        }
+        if dir.join("partition").exists()
+            && let Some(disk) = dir.parent().and_then(Path::file_name)
+        {
+            return physical_disks(sys, &disk.to_string_lossy(), depth + 1);
+        }
+    }
+    BTreeSet::from([block.to_string()])
+}
+
+/// Devices of the btrfs filesystem that `block` belongs to, `block` alone when none lists it.
+fn btrfs_pool(sys: &Path, block: &str) -> BTreeSet<String> {
+    let Ok(filesystems) = fs::read_dir(sys.join("fs/btrfs")) else {
+        return BTreeSet::from([block.to_string()]);
+    };
+    filesystems
+        .filter_map(Result::ok)
+        .map(|fs| entry_names(&fs.path().join("devices")))
+        .find(|devices| devices.contains(block))
+        .unwrap_or_else(|| BTreeSet::from([block.to_string()]))
+}
+
+fn entry_names(dir: &Path) -> BTreeSet<String> {
+    fs::read_dir(dir)
+        .map(|entries| entries.filter_map(Result::ok).map(|e| e.file_name().to_string_lossy().into_owned()).collect())
+        .unwrap_or_default()
+}
+
+/// Mount point, filesystem type and source of each line of a mountinfo file.
+fn parse_mountinfo(mountinfo: &str) -> Vec<(PathBuf, String, String)> {
+    let mut mounts = vec![];
+    for line in mountinfo.lines() {
+        let Some((fields, tail)) = line.split_once(" - ") else {
+            continue;
+        };
+        let (Some(mount_point), mut tail) = (fields.split(' ').nth(4), tail.split(' ')) else {
+            continue;
+        };
+        if let (Some(fs_type), Some(source)) = (tail.next(), tail.next()) {
+            mounts.push((PathBuf::from(unescape(mount_point)), fs_type.to_string(), unescape(source)));
+        }
+    }
+    mounts
+}
+
+/// Undoes the octal escapes of spaces, tabs, newlines and backslashes in mountinfo.
+fn unescape(field: &str) -> String {
+    let mut text = String::new();
+    let mut rest = field;
+    while let Some(at) = rest.find('\\') {
+        text.push_str(&rest[..at]);
+        match rest.get(at + 1..at + 4).and_then(|code| u8::from_str_radix(code, 8).ok()) {
+            Some(byte) => {
+                text.push(byte as char);
+                rest = &rest[at + 4..];
+            }
+            None => {
+                text.push('\\');
+                rest = &rest[at + 1..];
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+/// Type and source of the innermost mount holding `path`, the last one mounted winning.
+fn mount_source(mounts: &[(PathBuf, String, String)], path: &Path) -> Option<(String, String)> {
+    mounts
+        .iter()
+        .enumerate()
+        .filter(|(_, (mount_point, _, _))| path.starts_with(mount_point))
+        .max_by_key(|(order, (mount_point, _, _))| (mount_point.components().count(), *order))
+        .map(|(_, (_, fs_type, source))| (fs_type.clone(), source.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, os::unix::fs::symlink, process};
+
+    use super::*;
+
+    const MOUNTINFO: &str = "\
+22 1 252:1 / / rw,relatime shared:1 - ext4 /dev/mapper/vg0-root rw
+29 22 0:25 / /tmp rw,nosuid shared:12 - tmpfs tmpfs rw,size=8G
+41 22 0:45 /@media /mnt/media rw,relatime shared:30 - btrfs /dev/sdd rw,space_cache=v2,subvol=/@media
+42 41 0:52 / /mnt/media/TV\\040Shows rw,relatime shared:31 - nfs nas:/export/tv rw,vers=4.2
+";
+
+    #[test]
+    fn finds_the_mount_of_a_path() {
+        let mounts = parse_mountinfo(MOUNTINFO);
+        assert_eq!(mounts.len(), 4);
+        assert_eq!(mounts[3].0, Path::new("/mnt/media/TV Shows"));
+        let source = |path: &str| mount_source(&mounts, Path::new(path));
+        assert_eq!(source("/home/me/in.mkv"), Some(("ext4".to_string(), "/dev/mapper/vg0-root".to_string())));
+        assert_eq!(source("/mnt/media/Films/in.mkv"), Some(("btrfs".to_string(), "/dev/sdd".to_string())));
+        assert_eq!(source("/mnt/media/TV Shows/in.mkv"), Some(("nfs".to_string(), "nas:/export/tv".to_string())));
+        // Only whole components match.
+        assert_eq!(source("/tmpfiles/a.mkv").unwrap().0, "ext4");
+        assert_eq!(unescape(r"a\134b\040c\"), r"a\b c\");
+    }
+
+    #[test]
+    fn follows_stacked_devices_to_their_disks() {
+        let sys = env::temp_dir().join(format!("fmuxie-{}-sys", process::id()));
+        let device = |path: &str| {
+            fs::create_dir_all(sys.join("devices").join(path)).unwrap();
+            let name = Path::new(path).file_name().unwrap();
+            fs::create_dir_all(sys.join("class/block")).unwrap();
+            symlink(sys.join("devices").join(path), sys.join("class/block").join(name)).unwrap();
+        };
+        let slave = |holder: &str, slave: &str| {
+            let slaves = sys.join("devices/virtual/block").join(holder).join("slaves");
+            fs::create_dir_all(&slaves).unwrap();
+            symlink(sys.join("class/block").join(slave), slaves.join(slave)).unwrap();
+        };
+        for disk in ["sda", "sdb", "sdc", "sdd", "sde", "nvme0n1"] {
+            device(&format!("pci/block/{disk}"));
+        }
+        for partition in ["sda/sda1", "sda/sda2", "sdb/sdb1", "sdc/sdc1", "nvme0n1/nvme0n1p1"] {
+            device(&format!("pci/block/{partition}"));
+            fs::write(sys.join("devices/pci/block").join(partition).join("partition"), "1").unwrap();
+        }
+        for holder in ["dm-0", "dm-1", "dm-2", "md0"] {
+            device(&format!("virtual/block/{holder}"));
+        }
+        // Two LVs of one LUKS volume on sda2, and an md mirror of sdb1 and sdc1.
+        slave("dm-0", "sda2");
+        slave("dm-1", "dm-0");
+        slave("dm-2", "dm-0");
+        slave("md0", "sdb1");
+        slave("md0", "sdc1");
+        fs::create_dir_all(sys.join("fs/btrfs/0b6f/devices")).unwrap();
+        symlink(sys.join("class/block/sdd"), sys.join("fs/btrfs/0b6f/devices/sdd")).unwrap();
+        symlink(sys.join("class/block/sde"), sys.join("fs/btrfs/0b6f/devices/sde")).unwrap();
+
+        let disks = |block: &str| physical_disks(&sys, block, 0).into_iter().collect::<Vec<_>>();
+        let dm1 = disks("dm-1");
+        let dm2 = disks("dm-2");
+        let md0 = disks("md0");
+        let nvme = disks("nvme0n1p1");
+        let pool = btrfs_pool(&sys, "sdd").into_iter().collect::<Vec<_>>();
+        let alone = btrfs_pool(&sys, "sda1").into_iter().collect::<Vec<_>>();
+        let unknown = disks("loop0");
+        fs::remove_dir_all(&sys).ok();
+        assert_eq!(dm1, ["sda"]);
+        assert_eq!(dm2, ["sda"]);
+        assert_eq!(md0, ["sdb", "sdc"]);
+        assert_eq!(nvme, ["nvme0n1"]);
+        assert_eq!(pool, ["sdd", "sde"]);
+        assert_eq!(alone, ["sda1"]);
+        assert_eq!(unknown, ["loop0"]);
+    }
+}
@@ -0,0 +1,225 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ffmpeg::runner::RemuxOutcome, remux::plan::RemuxPlan};
+
+/// Why ffmpeg failed, read from its exit status and the end of its stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Failure {
+    /// Missing, unreadable or corrupt input.
+    InputUnreadable,
+    /// The output container cannot hold a codec, with the input stream when ffmpeg names it.
+    UnsupportedCodec { stream: Option<usize> },
+    DiskFull,
+    /// Read or write error that may not happen again, such as a network share dropping.
+    Io,
+    /// ffmpeg was stopped by a signal it did not get from us.
+    Killed,
+    Unknown,
+}
+
+impl Failure {
+    /// Classifies a failed run of the job with the given plan, `None` for extractions.
+    pub fn classify(outcome: &RemuxOutcome, plan: Option<&RemuxPlan>, input: &Path) -> Failure {
+        let stderr = outcome.stderr_tail.join("\n").to_lowercase();
+        let has = |patterns: &[&str]| patterns.iter().any(|p| stderr.contains(p));
+        if outcome.exit_code.is_none() || has(&["received signal"]) {
+            return Failure::Killed;
+        }
+        if has(&["no space left on device", "disk quota exceeded"]) {
+            return Failure::DiskFull;
+        }
+        if has(UNSUPPORTED) {
+            // The stream mapping printed before the error names streams too.
+            let stream = stderr
+                .lines()
+                .filter(|line| UNSUPPORTED.iter().any(|p| line.contains(p)))
+                .find_map(output_stream)
+                .and_then(|index| plan.and_then(|p| p.output_streams().get(index).map(|s| s.source)));
+            return Failure::UnsupportedCodec { stream };
+        }
+        if has(&["input/output error", "stale file handle", "connection reset", "connection timed out"]) {
+            return Failure::Io;
+        }
+        let input = input.to_string_lossy().to_lowercase();
+        let input_error = outcome.stderr_tail.iter().map(|l| l.to_lowercase()).any(|line| {
+            line.contains(&input) && (line.contains("no such file or directory") || line.contains("permission denied"))
+        });
+        if input_error
+            || has(&["invalid data found when processing input", "moov atom not found", "error opening input"])
+        {
+            return Failure::InputUnreadable;
+        }
+        Failure::Unknown
+    }
+
+    pub fn describe(self) -> String {
+        match self {
+            Failure::InputUnreadable => "input unreadable".to_string(),
+            Failure::UnsupportedCodec { stream: Some(stream) } => {
+                format!("stream {stream} not supported by the container")
+            }
+            Failure::UnsupportedCodec { stream: None } => "codec not supported by the container".to_string(),
+            Failure::DiskFull => "disk full".to_string(),
+            Failure::Io => "I/O error".to_string(),
+            Failure::Killed => "killed".to_string(),
+            Failure::Unknown => "ffmpeg error".to_string(),
+        }
+    }
+
+    /// Failures worth running the job again for without changing anything.
+    pub fn is_transient(self) -> bool {
+        matches!(self, Failure::Io | Failure::Killed)
+    }
+}
+
+const UNSUPPORTED: &[&str] = &[
+    "could not find tag for codec",
+    "not currently supported in container",
+    "are supported for",
+    "is not supported in",
+    "unsupported codec",
+];
+
+/// Index of the output stream in messages such as `Could not find tag for codec pcm_s24le in stream #1`.
+fn output_stream(line: &str) -> Option<usize> {
+    let rest = &line[line.find("stream #")? + "stream #".len()..];
+    let rest = rest
+        .split_once(':')
+        .filter(|(file, _)| !file.is_empty() && file.chars().all(|c| c.is_ascii_digit()))
+        .map_or(rest, |(_, s)| s);
+    let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+/// Change to a failed remux that avoids its failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fix {
+    /// Matroska holds about every codec.
+    Matroska,
+    DropStream(usize),
+}
+
+impl Fix {
+    pub fn for_failure(failure: Failure, plan: &RemuxPlan) -> Option<Fix> {
+        match failure {
+            // An in-place output keeps the extension of the file it replaces.
+            Failure::UnsupportedCodec { .. } if plan.container() != "mkv" && !plan.in_place => Some(Fix::Matroska),
+            Failure::UnsupportedCodec { stream } => stream.map(Fix::DropStream),
+            _ => None,
+        }
+    }
+
+    pub fn describe(self) -> String {
+        match self {
+            Fix::Matroska => "switch to MKV".to_string(),
+            Fix::DropStream(stream) => format!("drop stream {stream}"),
+        }
+    }
+
+    pub fn apply(self, plan: &mut RemuxPlan) {
+        match self {
+            Fix::Matroska => {
+                plan.output.set_extension("mkv");
+            }
+            Fix::DropStream(stream) => {
+                if !plan.dropped.contains(&stream) {
+                    plan.dropped.push(stream);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::ffmpeg::probe::ProbeInfo;
+
+    const PROBE: &str = r#"{"streams": [
+        {"index": 0, "codec_type": "video", "codec_name": "h264"},
+        {"index": 1, "codec_type": "audio", "codec_name": "aac", "tags": {"language": "eng"}},
+        {"index": 2, "codec_type": "audio", "codec_name": "pcm_s24le", "tags": {"language": "jpn"}}
+    ]}"#;
+
+    fn failed(exit_code: Option<i32>, stderr: &str) -> RemuxOutcome {
+        RemuxOutcome {
+            success: false,
+            cancelled: false,
+            exit_code,
+            stderr_tail: stderr.lines().map(str::to_string).collect(),
+        }
+    }
+
+    #[test]
+    fn maps_the_unsupported_output_stream_to_its_input() {
+        let probe: ProbeInfo = serde_json::from_str(PROBE).unwrap();
+        let mut plan = RemuxPlan::new(PathBuf::from("/films/in.mkv"), PathBuf::from("/films/out.mp4"), probe);
+        plan.dropped.push(1);
+        let outcome = failed(
+            Some(234),
+            "Stream mapping:
+  Stream #0:0 -> #0:0 (copy)
+  Stream #0:2 -> #0:1 (copy)
+[mp4 @ 0x5581c8a0] Could not find tag for codec pcm_s24le in stream #1, codec not currently supported in container
+[out#0/mp4 @ 0x5581c840] Could not write header (incorrect codec parameters ?): Invalid argument",
+        );
+        let failure = Failure::classify(&outcome, Some(&plan), &plan.input);
+        assert_eq!(failure, Failure::UnsupportedCodec { stream: Some(2) });
+        assert_eq!(failure.describe(), "stream 2 not supported by the container");
+        // Extractions have no plan to map the stream through.
+        assert_eq!(Failure::classify(&outcome, None, &plan.input), Failure::UnsupportedCodec { stream: None });
+    }
+
+    #[test]
+    fn classifies_common_errors() {
+        let input = Path::new("/mnt/share/Film.mkv");
+        let classify = |code, stderr| Failure::classify(&failed(code, stderr), None, input);
+        assert_eq!(classify(None, ""), Failure::Killed);
+        assert_eq!(classify(Some(255), "Exiting normally, received signal 15."), Failure::Killed);
+        assert_eq!(
+            classify(Some(1), "[matroska @ 0x55d0] Error writing packet: No space left on device"),
+            Failure::DiskFull
+        );
+        assert_eq!(
+            classify(Some(1), "[in#0/matroska,webm @ 0x55d0] Error during demuxing: Input/output error"),
+            Failure::Io
+        );
+        assert_eq!(classify(Some(1), "/mnt/share/Film.mkv: No such file or directory"), Failure::InputUnreadable);
+        // The same error about another file, such as a sidecar, says nothing about the input.
+        assert_eq!(classify(Some(1), "/mnt/share/Film.en.srt: No such file or directory"), Failure::Unknown);
+        assert_eq!(
+            classify(Some(1), "/mnt/share/Film.mkv: Invalid data found when processing input"),
+            Failure::InputUnreadable
+        );
+        assert!(Failure::Io.is_transient() && !Failure::DiskFull.is_transient());
+    }
+
+    #[test]
+    fn reads_output_stream_indexes() {
+        assert_eq!(output_stream("could not find tag for codec pcm_s24le in stream #1, codec not"), Some(1));
+        assert_eq!(output_stream("codec for stream #0:3 is not supported in mp4"), Some(3));
+        assert_eq!(output_stream("unsupported codec"), None);
+    }
+
+    #[test]
+    fn offers_matroska_before_dropping_the_stream() {
+        let probe: ProbeInfo = serde_json::from_str(PROBE).unwrap();
+        let mut plan = RemuxPlan::new(PathBuf::from("in.mkv"), PathBuf::from("out.mp4"), probe);
+        let failure = Failure::UnsupportedCodec { stream: Some(2) };
+        assert_eq!(Fix::for_failure(failure, &plan), Some(Fix::Matroska));
+        Fix::Matroska.apply(&mut plan);
+        assert_eq!(plan.output, PathBuf::from("out.mkv"));
+
+        assert_eq!(Fix::for_failure(failure, &plan), Some(Fix::DropStream(2)));
+        assert_eq!(Fix::for_failure(Failure::UnsupportedCodec { stream: None }, &plan), None);
+        assert_eq!(Fix::for_failure(Failure::DiskFull, &plan), None);
+        Fix::DropStream(2).apply(&mut plan);
+        Fix::DropStream(2).apply(&mut plan);
+        assert_eq!(plan.dropped, [2]);
+    }
+}
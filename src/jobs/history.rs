@@ -0,0 +1,94 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::config_dir,
+    ffmpeg::{command_line, runner},
+    units::local_time,
+};
+
+use super::job::{Job, JobStatus};
+
+/// What happened to one executed job, appended to `history.jsonl` in the config directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Unix time the job finished at.
+    pub finished: u64,
+    pub input: PathBuf,
+    pub outputs: Vec<PathBuf>,
+    pub command: String,
+    /// Seconds ffmpeg ran for.
+    pub duration: f64,
+    pub status: JobStatus,
+    pub exit_code: Option<i32>,
+    pub stderr_tail: Vec<String>,
+    pub verification: Option<String>,
+    pub message: Option<String>,
+}
+
+impl HistoryEntry {
+    /// Entry of a job that just stopped.
+    pub fn of(job: &Job) -> HistoryEntry {
+        let outcome = job.outcome.as_ref();
+        HistoryEntry {
+            finished: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            input: job.input().to_path_buf(),
+            outputs: job.outputs(),
+            command: command_line::to_shell(&runner::command_line(&job.ffmpeg_args())),
+            duration: job.started.map(|s| s.elapsed().as_secs_f64()).unwrap_or(0.0),
+            status: job.status,
+            exit_code: outcome.and_then(|o| o.exit_code),
+            stderr_tail: outcome.map(|o| o.stderr_tail.clone()).unwrap_or_default(),
+            verification: job.verdict.as_ref().map(|v| v.describe()),
+            message: job.message.clone(),
+        }
+    }
+
+    pub fn when(&self) -> String {
+        local_time(self.finished)
+    }
+
+    /// Whether every word of `filter` appears in the date, paths, status or message.
+    pub fn matches(&self, filter: &str) -> bool {
+        let text = format!(
+            "{} {} {} {} {} {}",
+            self.when(),
+            self.input.display(),
+            self.outputs.iter().map(|o| o.display().to_string()).collect::<Vec<_>>().join(" "),
+            self.status.label(),
+            self.verification.as_deref().unwrap_or_default(),
+            self.message.as_deref().unwrap_or_default()
+        )
+        .to_lowercase();
+        filter.to_lowercase().split_whitespace().all(|word| text.contains(word))
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("history.jsonl"))
+}
+
+pub fn append(entry: &HistoryEntry) -> io::Result<()> {
+    let path = history_path().ok_or_else(|| io::Error::other("no config directory"))?;
+    fs::create_dir_all(path.parent().expect("history path has a parent"))?;
+    let mut line = serde_json::to_vec(entry).map_err(io::Error::other)?;
+    line.push(b'\n');
+    OpenOptions::new().create(true).append(true).open(path)?.write_all(&line)
+}
+
+/// Every recorded entry, newest first. Lines that do not parse, such as one cut short by a
+/// crash, are skipped.
+pub fn load() -> Vec<HistoryEntry> {
+    let Some(text) = history_path().and_then(|path| fs::read_to_string(path).ok()) else {
+        return vec![];
+    };
+    let mut entries: Vec<HistoryEntry> = text.lines().filter_map(|line| serde_json::from_str(line).ok()).collect();
+    entries.reverse();
+    entries
+}
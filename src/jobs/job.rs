@@ -0,0 +1,180 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    device,
+    failure::{Failure, Fix},
+};
+
+use crate::{
+    ffmpeg::{progress::RemuxProgress, runner::RemuxOutcome},
+    remux::{extract::ExtractPlan, plan::RemuxPlan, split, verify::Verdict},
+};
+
+pub type JobId = u64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            JobStatus::Pending => "pending",
+            JobStatus::Running => "running",
+            JobStatus::Done => "done",
+            JobStatus::Failed => "failed",
+            JobStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn is_finished(self) -> bool {
+        matches!(self, JobStatus::Done | JobStatus::Failed | JobStatus::Cancelled)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum JobKind {
+    Remux(Box<RemuxPlan>),
+    Extract(ExtractPlan),
+}
+
+/// One ffmpeg run of the batch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: JobId,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    /// Result shown in the Jobs pane once the job stopped.
+    pub message: Option<String>,
+    pub outcome: Option<RemuxOutcome>,
+    /// Comparison of a finished remux with its plan.
+    pub verdict: Option<Verdict>,
+    /// Why the last ffmpeg run failed.
+    #[serde(default)]
+    pub failure: Option<Failure>,
+    /// Failed runs since the job was queued, bounded by `max_retries` for transient failures.
+    #[serde(default)]
+    pub attempts: u32,
+    /// The verified output of an in-place remux is being swapped with the input; saved
+    /// first so that a crash during the swap does not run the job again on the new file.
+    #[serde(default)]
+    pub replacing: bool,
+    #[serde(skip)]
+    pub progress: RemuxProgress,
+    #[serde(skip)]
+    pub started: Option<Instant>,
+}
+
+impl Job {
+    pub fn new(id: JobId, kind: JobKind) -> Job {
+        Job {
+            id,
+            kind,
+            status: JobStatus::Pending,
+            message: None,
+            outcome: None,
+            verdict: None,
+            failure: None,
+            attempts: 0,
+            replacing: false,
+            progress: RemuxProgress::default(),
+            started: None,
+        }
+    }
+
+    pub fn plan(&self) -> Option<&RemuxPlan> {
+        match &self.kind {
+            JobKind::Remux(plan) => Some(plan),
+            JobKind::Extract(_) => None,
+        }
+    }
+
+    pub fn input(&self) -> &Path {
+        match &self.kind {
+            JobKind::Remux(plan) => &plan.input,
+            JobKind::Extract(plan) => &plan.input,
+        }
+    }
+
+    /// Files written by ffmpeg, split outputs as their part pattern.
+    pub fn outputs(&self) -> Vec<PathBuf> {
+        match &self.kind {
+            JobKind::Remux(plan) => vec![plan.written_output()],
+            JobKind::Extract(plan) => plan.outputs.iter().map(|o| o.path.clone()).collect(),
+        }
+    }
+
+    pub fn duration(&self) -> Option<f64> {
+        match &self.kind {
+            JobKind::Remux(plan) => plan.duration(),
+            JobKind::Extract(plan) => plan.duration,
+        }
+    }
+
+    pub fn ffmpeg_args(&self) -> Vec<String> {
+        match &self.kind {
+            JobKind::Remux(plan) => plan.ffmpeg_args(),
+            JobKind::Extract(plan) => plan.ffmpeg_args(),
+        }
+    }
+
+    /// Disks the job reads from or writes to.
+    pub fn disks(&self) -> BTreeSet<String> {
+        let mut paths = self.outputs();
+        paths.push(self.input().to_path_buf());
+        if let JobKind::Remux(plan) = &self.kind {
+            paths.extend(plan.segments.iter().map(|s| s.path.clone()));
+            paths.extend(plan.sidecars.iter().map(|s| s.path.clone()));
+        }
+        paths.iter().flat_map(|p| device::disks_of(p)).collect()
+    }
+
+    /// Change that would avoid the failure of a failed remux.
+    pub fn fix(&self) -> Option<Fix> {
+        match self.status {
+            JobStatus::Failed => Fix::for_failure(self.failure?, self.plan()?),
+            _ => None,
+        }
+    }
+
+    pub fn ratio(&self) -> Option<f64> {
+        match self.status {
+            JobStatus::Done => Some(1.0),
+            JobStatus::Pending => Some(0.0),
+            _ => self.progress.ratio(self.duration()),
+        }
+    }
+
+    /// Starts over, forgetting the previous attempt.
+    pub fn reset(&mut self) {
+        self.status = JobStatus::Pending;
+        self.message = None;
+        self.outcome = None;
+        self.verdict = None;
+        self.failure = None;
+        self.replacing = false;
+        self.progress = RemuxProgress::default();
+        self.started = None;
+    }
+}
+
+/// Deletes what a failed or interrupted job wrote, every part of a split output included.
+pub fn remove_partial_outputs(paths: &[PathBuf]) {
+    for path in paths.iter().flat_map(|p| split::expand(p)).filter(|p| p.exists()) {
+        fs::remove_file(path).ok();
+    }
+}
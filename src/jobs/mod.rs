@@ -0,0 +1,5 @@
+pub mod device;
+pub mod failure;
+pub mod history;
+pub mod job;
+pub mod queue;
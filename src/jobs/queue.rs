@@ -0,0 +1,234 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config::config_dir, remux::verify};
+
+use super::job::{self, Job, JobId, JobKind, JobStatus};
+
+/// Jobs of the batch in the order they run, stored as JSON in the config directory so
+/// the batch survives a restart.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JobQueue {
+    next_id: JobId,
+    pub jobs: Vec<Job>,
+    /// Pending jobs wait until the user starts the queue.
+    #[serde(skip)]
+    pub held: bool,
+}
+
+impl JobQueue {
+    /// Queue saved by the last session, jobs still marked running were interrupted by a crash.
+    /// Pending jobs are held until the user starts them. A file that cannot be read is moved
+    /// to `jobs.json.bad` so that saving the empty queue does not lose it.
+    pub fn load() -> Result<JobQueue, String> {
+        match queue_path() {
+            Some(path) => JobQueue::load_from(&path),
+            None => Ok(JobQueue::default()),
+        }
+    }
+
+    fn load_from(path: &Path) -> Result<JobQueue, String> {
+        let loaded = match fs::read(path) {
+            Ok(bytes) => serde_json::from_slice::<JobQueue>(&bytes).map_err(|err| err.to_string()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(JobQueue::default()),
+            Err(err) => Err(err.to_string()),
+        };
+        match loaded {
+            Ok(mut queue) => {
+                queue.held = true;
+                Ok(queue)
+            }
+            Err(err) => {
+                let bad = path.with_extension("json.bad");
+                Err(match fs::rename(path, &bad) {
+                    Ok(()) => format!("Job queue unreadable ({err}), moved to {}", bad.display()),
+                    Err(rename) => format!("Job queue unreadable ({err}) and not moved aside: {rename}"),
+                })
+            }
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = queue_path().ok_or_else(|| io::Error::other("no config directory"))?;
+        self.save_to(&path)
+    }
+
+    fn save_to(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path.parent().expect("queue path has a parent"))?;
+        let json = serde_json::to_vec_pretty(self).map_err(io::Error::other)?;
+        // Written aside first so a crash while saving cannot truncate the queue.
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, json)?;
+        fs::rename(temp, path)
+    }
+
+    /// Settles the jobs still marked running by a crash. An output that verifies completes
+    /// its job, others are deleted and their job is pending again. In-place outputs still
+    /// had to replace their input, so those jobs always run again. Returns how many jobs
+    /// were reset and how many were recovered.
+    pub fn reset_interrupted(&mut self) -> (usize, usize) {
+        let (mut interrupted, mut recovered) = (0, 0);
+        for job in self.jobs.iter_mut().filter(|j| j.status == JobStatus::Running) {
+            let verdict = job.plan().filter(|p| !p.in_place).map(verify::verify);
+            match verdict {
+                Some(verdict) if verdict.passed() => {
+                    job.status = JobStatus::Done;
+                    job.verdict = Some(verdict);
+                    job.message = Some("Finished before the interruption, verified".to_string());
+                    recovered += 1;
+                }
+                _ => {
+                    job::remove_partial_outputs(&job.outputs());
+                    job.reset();
+                    interrupted += 1;
+                }
+            }
+        }
+        (interrupted, recovered)
+    }
+
+    pub fn push(&mut self, mut kind: JobKind) -> JobId {
+        self.next_id += 1;
+        if let JobKind::Remux(plan) = &mut kind {
+            plan.job = Some(self.next_id);
+        }
+        self.jobs.push(Job::new(self.next_id, kind));
+        self.next_id
+    }
+
+    pub fn get(&self, id: JobId) -> Option<&Job> {
+        self.jobs.iter().find(|j| j.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: JobId) -> Option<&mut Job> {
+        self.jobs.iter_mut().find(|j| j.id == id)
+    }
+
+    pub fn pending_ids(&self) -> Vec<JobId> {
+        self.jobs.iter().filter(|j| j.status == JobStatus::Pending).map(|j| j.id).collect()
+    }
+
+    pub fn pending(&self) -> usize {
+        self.jobs.iter().filter(|j| j.status == JobStatus::Pending).count()
+    }
+
+    /// Puts a failed or cancelled job back in the queue, with its retries available again.
+    pub fn retry(&mut self, id: JobId) -> bool {
+        match self.get_mut(id) {
+            Some(job) if matches!(job.status, JobStatus::Failed | JobStatus::Cancelled) => {
+                job.reset();
+                job.attempts = 0;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Forgets a job that is not running.
+    pub fn remove(&mut self, id: JobId) -> bool {
+        let before = self.jobs.len();
+        self.jobs.retain(|j| j.id != id || j.status == JobStatus::Running);
+        self.jobs.len() != before
+    }
+}
+
+fn queue_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("jobs.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+    use crate::{ffmpeg::probe::ProbeInfo, remux::plan::RemuxPlan};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("fmuxie-{}-{name}", process::id()));
+        fs::remove_dir_all(&dir).ok();
+        dir
+    }
+
+    fn remux(dir: &Path, output: &str) -> JobKind {
+        JobKind::Remux(Box::new(RemuxPlan::new(dir.join("in.mkv"), dir.join(output), ProbeInfo::default())))
+    }
+
+    #[test]
+    fn saves_and_loads_the_queue() {
+        let dir = temp_dir("queue-round-trip");
+        let path = dir.join("fmuxie/jobs.json");
+        let mut queue = JobQueue::default();
+        let done = queue.push(remux(&dir, "a.mkv"));
+        queue.push(remux(&dir, "b.mkv"));
+        queue.get_mut(done).unwrap().status = JobStatus::Done;
+        queue.save_to(&path).unwrap();
+        // Saving a shorter queue replaces the whole file.
+        queue.remove(done);
+        queue.save_to(&path).unwrap();
+        let mut loaded = JobQueue::load_from(&path).unwrap();
+        let left: Vec<_> = fs::read_dir(path.parent().unwrap()).unwrap().map(|e| e.unwrap().file_name()).collect();
+        fs::remove_dir_all(&dir).ok();
+        assert_eq!(left, ["jobs.json"]);
+        assert!(loaded.held);
+        assert_eq!(loaded.jobs.len(), 1);
+        assert_eq!(loaded.pending_ids(), [2]);
+        assert_eq!(loaded.get(2).unwrap().plan().unwrap().output, dir.join("b.mkv"));
+        // Ids are not handed out twice across sessions.
+        assert_eq!(loaded.push(remux(&dir, "c.mkv")), 3);
+    }
+
+    #[test]
+    fn moves_an_unreadable_queue_aside() {
+        let dir = temp_dir("queue-bad");
+        let path = dir.join("jobs.json");
+        let missing = JobQueue::load_from(&path);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, "{\"jobs\": [").unwrap();
+        let err = JobQueue::load_from(&path).unwrap_err();
+        let kept = fs::read_to_string(dir.join("jobs.json.bad")).unwrap();
+        let moved = !path.exists();
+        fs::remove_dir_all(&dir).ok();
+        assert!(missing.is_ok_and(|queue| queue.jobs.is_empty() && !queue.held));
+        assert!(err.starts_with("Job queue unreadable"), "{err}");
+        assert!(err.ends_with("jobs.json.bad"), "{err}");
+        assert_eq!(kept, "{\"jobs\": [");
+        assert!(moved);
+    }
+
+    #[test]
+    fn resets_the_jobs_a_crash_left_running() {
+        let dir = temp_dir("queue-running");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("jobs.json");
+        let mut queue = JobQueue::default();
+        let unwritten = queue.push(remux(&dir, "a.mkv"));
+        let partial = queue.push(remux(&dir, "b.mkv"));
+        let done = queue.push(remux(&dir, "c.mkv"));
+        for id in [unwritten, partial] {
+            let job = queue.get_mut(id).unwrap();
+            job.status = JobStatus::Running;
+            job.message = Some("remuxing".to_string());
+        }
+        queue.get_mut(done).unwrap().status = JobStatus::Done;
+        fs::write(dir.join("b.mkv"), "cut short").unwrap();
+        fs::write(dir.join("c.mkv"), "finished").unwrap();
+        queue.save_to(&path).unwrap();
+
+        let mut loaded = JobQueue::load_from(&path).unwrap();
+        let counts = loaded.reset_interrupted();
+        let partial_left = dir.join("b.mkv").exists();
+        let done_left = dir.join("c.mkv").exists();
+        fs::remove_dir_all(&dir).ok();
+        assert_eq!(counts, (2, 0));
+        assert_eq!(loaded.pending_ids(), [unwritten, partial]);
+        assert_eq!(loaded.get(partial).unwrap().message, None);
+        assert_eq!(loaded.get(done).unwrap().status, JobStatus::Done);
+        assert!(!partial_left);
+        assert!(done_left);
+    }
+}
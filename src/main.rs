@@ -1,7 +1,15 @@
 mod app;
 mod components;
+mod config;
 mod events;
+mod ffmpeg;
+mod jobs;
+mod matroska;
+mod mp4;
+mod remux;
+mod scan;
 mod theme;
+mod units;
 pub mod state;
 pub mod focus_manager;
 
@@ -31,37 +39,26 @@ fn main() -> Result<(), io::Error> {
     let stdout: io::Stdout = io::stdout();
     let backend: CrosstermBackend<io::Stdout> = CrosstermBackend::new(stdout);
     let mut terminal: Terminal<CrosstermBackend<io::Stdout>> = Terminal::new(backend)?;// Terminal<CrosstermBackend<io::Stdout>>
-    let mut app = App::new();
-  
     let event_queue = EventQueue::new();
+    let mut app = App::new(event_queue.sender.clone());
 
     // Main loop
     loop {
         terminal.draw(|f| {
             if let Err(err) = app.render(f) {
-                eprintln!("error: {}", err.to_string());
+                eprintln!("error: {}", err);
                 std::process::exit(1);
             }
         })?;
 
         match event_queue.receiver.recv() {
             Ok(event) => {
-                match &event {
-                    AppEvent::Input(key) => {
-                        match key.code {
-                            // 🔚 Quitter l'app
-                            crossterm::event::KeyCode::Char('q') | crossterm::event::KeyCode::Esc => break,
-                            _ => {}
-                        }
-                    }
-                    AppEvent::Tick => {
-                        // tu peux faire un refresh, mise à jour de l’état, etc.
-                    }
-                    _ => {}
-                }
-
                 // 📥 Envoyer l'événement au widget
                 app.handle_event(&event);
+                // 🔚 Quitter l'app
+                if app.should_quit() {
+                    break;
+                }
             }
             Err(err) => {
                 eprintln!("Erreur dans la file d'événements : {err}");
@@ -75,7 +72,7 @@ fn main() -> Result<(), io::Error> {
   
     terminal.draw(|f| {
         if let Err(err) = app.render(f) {
-            eprintln!("error: {}", err.to_string());
+            eprintln!("error: {}", err);
             std::process::exit(1);
         }
     })?;
@@ -0,0 +1,235 @@
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+};
+
+pub const EBML: u32 = 0x1A45DFA3;
+pub const SEGMENT: u32 = 0x18538067;
+pub const SEEK_HEAD: u32 = 0x114D9B74;
+pub const SEEK: u32 = 0x4DBB;
+pub const SEEK_ID: u32 = 0x53AB;
+pub const SEEK_POSITION: u32 = 0x53AC;
+pub const CLUSTER: u32 = 0x1F43B675;
+pub const TRACKS: u32 = 0x1654AE6B;
+pub const TRACK_ENTRY: u32 = 0xAE;
+pub const TRACK_UID: u32 = 0x73C5;
+pub const TAGS: u32 = 0x1254C367;
+pub const TAG: u32 = 0x7373;
+pub const TARGETS: u32 = 0x63C0;
+pub const TAG_TRACK_UID: u32 = 0x63C5;
+pub const SIMPLE_TAG: u32 = 0x67C8;
+pub const TAG_NAME: u32 = 0x45A3;
+pub const TAG_STRING: u32 = 0x4487;
+pub const TAG_BINARY: u32 = 0x4485;
+pub const VOID: u32 = 0xEC;
+pub const CRC32: u32 = 0xBF;
+pub const FLAG_DEFAULT: u32 = 0x88;
+pub const FLAG_FORCED: u32 = 0x55AA;
+pub const NAME: u32 = 0x536E;
+pub const LANGUAGE: u32 = 0x22B59C;
+pub const LANGUAGE_BCP47: u32 = 0x22B59D;
+
+/// Header of an element: where it starts, how long its ID and size are, and its data size.
+#[derive(Debug, Clone, Copy)]
+pub struct Element {
+    pub id: u32,
+    pub offset: u64,
+    pub header_len: usize,
+    /// `None` for the "unknown size" of live streams.
+    pub size: Option<u64>,
+}
+
+impl Element {
+    pub fn data_start(&self) -> u64 {
+        self.offset + self.header_len as u64
+    }
+
+    pub fn end(&self) -> Option<u64> {
+        Some(self.data_start() + self.size?)
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Reads a variable size integer, returning its value without the length marker and its length.
+fn read_vint(bytes: &[u8]) -> io::Result<(u64, usize, bool)> {
+    let first = *bytes.first().ok_or_else(|| invalid("truncated element"))?;
+    if first == 0 {
+        return Err(invalid("invalid EBML length"));
+    }
+    let len = first.leading_zeros() as usize + 1;
+    let raw = bytes.get(..len).ok_or_else(|| invalid("truncated element"))?;
+    // Eight byte integers have their marker in the last bit of the first byte and no value bits.
+    let mut value = (first as u16 & (0xFF >> len)) as u64;
+    for byte in &raw[1..] {
+        value = (value << 8) | *byte as u64;
+    }
+    let unknown = value == (1 << (7 * len)) - 1;
+    Ok((value, len, unknown))
+}
+
+/// Parses the element header at the start of `bytes`.
+pub fn parse_header(bytes: &[u8], offset: u64) -> io::Result<Element> {
+    let first = *bytes.first().ok_or_else(|| invalid("truncated element"))?;
+    let id_len = first.leading_zeros() as usize + 1;
+    if id_len > 4 {
+        return Err(invalid("invalid EBML ID"));
+    }
+    let id = bytes
+        .get(..id_len)
+        .ok_or_else(|| invalid("truncated element"))?
+        .iter()
+        .fold(0u32, |id, byte| (id << 8) | *byte as u32);
+    let (size, size_len, unknown) = read_vint(&bytes[id_len..])?;
+    Ok(Element { id, offset, header_len: id_len + size_len, size: (!unknown).then_some(size) })
+}
+
+pub fn read_header(file: &mut File, offset: u64) -> io::Result<Element> {
+    let mut bytes = [0u8; 12];
+    file.seek(SeekFrom::Start(offset))?;
+    let mut filled = 0;
+    while filled < bytes.len() {
+        match file.read(&mut bytes[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    parse_header(&bytes[..filled], offset)
+}
+
+/// Splits the data of a master element into its children, each with its header.
+pub fn children(data: &[u8]) -> io::Result<Vec<(Element, &[u8])>> {
+    let mut children = vec![];
+    let mut pos = 0;
+    while pos < data.len() {
+        let element = parse_header(&data[pos..], pos as u64)?;
+        let size = element.size.ok_or_else(|| invalid("unknown size inside a header element"))?;
+        let raw = usize::try_from(size)
+            .ok()
+            .and_then(|size| pos.checked_add(element.header_len + size))
+            .and_then(|end| data.get(pos..end))
+            .ok_or_else(|| invalid("element overflows its parent"))?;
+        let end = pos + raw.len();
+        children.push((element, raw));
+        pos = end;
+    }
+    Ok(children)
+}
+
+/// Data of a child of the form returned by `children`.
+pub fn data<'a>(element: &Element, raw: &'a [u8]) -> &'a [u8] {
+    &raw[element.header_len..]
+}
+
+pub fn read_uint(data: &[u8]) -> u64 {
+    data.iter().fold(0, |value, byte| (value << 8) | *byte as u64)
+}
+
+fn encode_id(id: u32) -> Vec<u8> {
+    let bytes = id.to_be_bytes();
+    let skip = bytes.iter().take_while(|b| **b == 0).count();
+    bytes[skip..].to_vec()
+}
+
+/// Smallest number of bytes able to hold `size` as a known size.
+pub fn size_len(size: u64) -> usize {
+    (1..=8).find(|len| size < (1 << (7 * len)) - 1).unwrap_or(8)
+}
+
+pub fn encode_size(size: u64, len: usize) -> Vec<u8> {
+    let marked = size | (1 << (7 * len));
+    marked.to_be_bytes()[8 - len..].to_vec()
+}
+
+/// Element with its size written on `len` bytes, or the fewest possible when `None`.
+pub fn encode_element(id: u32, data: &[u8], len: Option<usize>) -> Vec<u8> {
+    let mut element = encode_id(id);
+    element.extend(encode_size(data.len() as u64, len.unwrap_or_else(|| size_len(data.len() as u64))));
+    element.extend(data);
+    element
+}
+
+pub fn encode_uint(id: u32, value: u64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take_while(|b| **b == 0).count().min(7);
+    encode_element(id, &bytes[skip..], None)
+}
+
+/// Void element taking exactly `total` bytes, `total` being at least 2.
+pub fn void(total: usize) -> Vec<u8> {
+    let len = if total - 2 < 127 { 1 } else { 8 };
+    encode_element(VOID, &vec![0; total - 1 - len], Some(len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_one_byte_sizes() {
+        assert_eq!(read_vint(&[0x81]).unwrap(), (1, 1, false));
+        assert_eq!(read_vint(&[0xFE]).unwrap(), (126, 1, false));
+        assert_eq!(read_vint(&[0xFF]).unwrap(), (127, 1, true));
+    }
+
+    #[test]
+    fn reads_eight_byte_sizes() {
+        // Segment size as written by mkvmerge and ffmpeg.
+        let bytes = [0x01, 0x00, 0x00, 0x00, 0x12, 0x34, 0x56, 0x78];
+        assert_eq!(read_vint(&bytes).unwrap(), (0x1234_5678, 8, false));
+        let unknown = [0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+        assert_eq!(read_vint(&unknown).unwrap(), ((1 << 56) - 1, 8, true));
+    }
+
+    #[test]
+    fn rejects_truncated_and_zero_sizes() {
+        assert!(read_vint(&[0x00, 0x81]).is_err());
+        assert!(read_vint(&[0x01, 0x00]).is_err());
+        assert!(read_vint(&[]).is_err());
+    }
+
+    #[test]
+    fn parses_segment_header() {
+        let bytes = [0x18, 0x53, 0x80, 0x67, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00];
+        let element = parse_header(&bytes, 40).unwrap();
+        assert_eq!(element.id, SEGMENT);
+        assert_eq!(element.header_len, 12);
+        assert_eq!(element.size, Some(0x1000));
+        assert_eq!(element.end(), Some(40 + 12 + 0x1000));
+    }
+
+    #[test]
+    fn encodes_sizes() {
+        assert_eq!(encode_size(5, 1), vec![0x85]);
+        assert_eq!(encode_size(5, 8), vec![0x01, 0, 0, 0, 0, 0, 0, 0x05]);
+        assert_eq!(size_len(126), 1);
+        assert_eq!(size_len(127), 2);
+        for (size, len) in [(0, 1), (126, 1), (300, 2), (0x1234_5678, 8)] {
+            assert_eq!(read_vint(&encode_size(size, len)).unwrap(), (size, len, false));
+        }
+    }
+
+    #[test]
+    fn void_takes_exactly_the_requested_room() {
+        for total in [2, 3, 128, 129, 130, 4096] {
+            let void = void(total);
+            assert_eq!(void.len(), total);
+            let element = parse_header(&void, 0).unwrap();
+            assert_eq!(element.id, VOID);
+            assert_eq!(element.header_len + element.size.unwrap() as usize, total);
+        }
+        assert_eq!(parse_header(&void(2), 0).unwrap().header_len, 2);
+        assert_eq!(parse_header(&void(200), 0).unwrap().header_len, 9);
+    }
+
+    #[test]
+    fn children_rejects_sizes_past_the_parent() {
+        let data = [0x88, 0x81, 0x01, 0xEC, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xF0];
+        assert!(children(&data).is_err());
+        let (element, raw) = children(&data[..3]).unwrap()[0];
+        assert_eq!(element.id, FLAG_DEFAULT);
+        assert_eq!(super::data(&element, raw), &[0x01]);
+    }
+}
@@ -0,0 +1,516 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use crate::remux::{edit::TrackEdit, sidecar};
+
+use super::ebml::{self, Element};
+
+/// Result of a header edit that did not fail outright.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderEdit {
+    Edited,
+    /// The new Tracks element does not fit in the old one and the padding after it.
+    NoRoom,
+    /// Tags of an edited track hold its title or language, and the Tags with the new values
+    /// do not fit in the old ones and the padding after them.
+    NoRoomForTags,
+}
+
+/// Rewrites the Tracks element of a Matroska file in place, the way mkvpropedit does:
+/// the new element takes the room of the old one plus the Void that follows it, so
+/// nothing else in the file moves. TITLE and LANGUAGE tags of the edited tracks are
+/// rewritten the same way. Stream indexes are the positions of the track entries,
+/// `tracks` is how many ffprobe reported and guards against a different numbering.
+pub fn edit_tracks(path: &Path, edits: &[TrackEdit], tracks_expected: usize) -> io::Result<HeaderEdit> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let (segment_end, tracks, tags) = locate(&mut file)?;
+    let size = tracks.size.ok_or_else(|| io::Error::other("Tracks element without size"))?;
+
+    let data = read_data(&mut file, &tracks, size)?;
+
+    let mut entries = vec![];
+    let mut retagged = vec![];
+    let mut index = 0;
+    for (element, raw) in ebml::children(&data)? {
+        match element.id {
+            // Checksums would no longer match, they are optional.
+            ebml::CRC32 => {}
+            ebml::TRACK_ENTRY => {
+                match edits.iter().find(|e| e.stream == index) {
+                    Some(edit) => {
+                        let uid = ebml::children(ebml::data(&element, raw))?
+                            .into_iter()
+                            .find(|(child, _)| child.id == ebml::TRACK_UID)
+                            .map(|(child, raw)| ebml::read_uint(ebml::data(&child, raw)));
+                        if let Some(uid) = uid {
+                            retagged.push((uid, edit));
+                        }
+                        entries.extend(edit_entry(ebml::data(&element, raw), edit)?)
+                    }
+                    None => entries.extend_from_slice(raw),
+                }
+                index += 1;
+            }
+            _ => entries.extend_from_slice(raw),
+        }
+    }
+
+    if index != tracks_expected {
+        return Err(io::Error::other(format!("{index} track entries but {tracks_expected} streams")));
+    }
+    let void = void_after(&mut file, &tracks, segment_end)?;
+    let Some(rewritten) = fill(ebml::TRACKS, &entries, room(&tracks, void.as_ref())) else {
+        return Ok(HeaderEdit::NoRoom);
+    };
+    let mut writes = vec![(tracks.offset, rewritten)];
+    for tags in &tags {
+        let data = read_data(&mut file, tags, tags.size.unwrap_or(0))?;
+        let Some(data) = retag(&data, &retagged)? else {
+            continue;
+        };
+        let room = room(tags, void_after(&mut file, tags, segment_end)?.as_ref());
+        // Tags must hold a Tag, a Void takes the place of Tags left empty.
+        let rewritten = if data.is_empty() { Some(ebml::void(room)) } else { fill(ebml::TAGS, &data, room) };
+        match rewritten {
+            Some(rewritten) => writes.push((tags.offset, rewritten)),
+            None => return Ok(HeaderEdit::NoRoomForTags),
+        }
+    }
+
+    for (offset, bytes) in writes {
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(&bytes)?;
+    }
+    file.sync_all()?;
+    Ok(HeaderEdit::Edited)
+}
+
+/// Bytes taken by `element` and the Void after it.
+fn room(element: &Element, void: Option<&Element>) -> usize {
+    let mut room = element.header_len + element.size.unwrap_or(0) as usize;
+    if let Some(void) = void {
+        room += void.header_len + void.size.unwrap_or(0) as usize;
+    }
+    room
+}
+
+/// Element holding `data` that takes exactly `room` bytes, padded with a Void, or `None`
+/// when it does not fit.
+fn fill(id: u32, data: &[u8], room: usize) -> Option<Vec<u8>> {
+    let mut element = ebml::encode_element(id, data, None);
+    if element.len() > room {
+        return None;
+    }
+    match room - element.len() {
+        0 => {}
+        // A Void takes at least two bytes, write the size on one more byte instead.
+        1 => {
+            let len = ebml::size_len(data.len() as u64) + 1;
+            if len > 8 {
+                return None;
+            }
+            element = ebml::encode_element(id, data, Some(len));
+        }
+        left => element.extend(ebml::void(left)),
+    }
+    Some(element)
+}
+
+/// Data of an element whose size is known to fit in the file.
+fn read_data(file: &mut File, element: &Element, size: u64) -> io::Result<Vec<u8>> {
+    if element.data_start() + size > file.metadata()?.len() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "element overflows the file"));
+    }
+    let mut data = vec![0; size as usize];
+    file.seek(SeekFrom::Start(element.data_start()))?;
+    file.read_exact(&mut data)?;
+    Ok(data)
+}
+
+/// Finds the end of the segment, the Tracks element and the Tags elements. Top-level
+/// elements are read up to the first Cluster, the SeekHeads among them tell where the
+/// others are; Tags written after the clusters of a file without SeekHead are not found.
+fn locate(file: &mut File) -> io::Result<(u64, Element, Vec<Element>)> {
+    let not_matroska = || io::Error::new(io::ErrorKind::InvalidData, "not a Matroska file");
+    let header = ebml::read_header(file, 0)?;
+    let segment = match header.end() {
+        Some(end) if header.id == ebml::EBML => ebml::read_header(file, end)?,
+        _ => return Err(not_matroska()),
+    };
+    if segment.id != ebml::SEGMENT {
+        return Err(not_matroska());
+    }
+    let file_len = file.metadata()?.len();
+    let segment_end = segment.end().map_or(file_len, |end| end.min(file_len));
+
+    let mut elements = vec![];
+    let mut pos = segment.data_start();
+    while pos < segment_end {
+        let element = ebml::read_header(file, pos)?;
+        if element.id == ebml::CLUSTER {
+            break;
+        }
+        elements.push(element);
+        match element.end() {
+            Some(end) => pos = end,
+            None => break,
+        }
+    }
+
+    // A SeekHead may point to another one, usually at the end of the file.
+    let mut seek_heads: Vec<Element> = elements.iter().filter(|e| e.id == ebml::SEEK_HEAD).copied().collect();
+    let mut read = 0;
+    while let Some(seek_head) = seek_heads.get(read).copied() {
+        read += 1;
+        for (id, position) in seek_entries(file, &seek_head)? {
+            let Some(offset) = segment.data_start().checked_add(position).filter(|o| *o < segment_end) else {
+                continue;
+            };
+            if elements.iter().any(|e| e.offset == offset) {
+                continue;
+            }
+            // Entries of files edited by other tools can be stale, trust only those that match.
+            let element = ebml::read_header(file, offset)?;
+            if element.id != id {
+                continue;
+            }
+            if id == ebml::SEEK_HEAD {
+                seek_heads.push(element);
+            }
+            elements.push(element);
+        }
+    }
+
+    let tracks = elements
+        .iter()
+        .find(|e| e.id == ebml::TRACKS)
+        .copied()
+        .ok_or_else(|| io::Error::other("no Tracks element found"))?;
+    let tags = elements.iter().filter(|e| e.id == ebml::TAGS && e.size.is_some()).copied().collect();
+    Ok((segment_end, tracks, tags))
+}
+
+/// Tracks, Tags and SeekHead entries of a SeekHead with their position in the segment.
+fn seek_entries(file: &mut File, seek_head: &Element) -> io::Result<Vec<(u32, u64)>> {
+    let Some(size) = seek_head.size else {
+        return Ok(vec![]);
+    };
+    let data = read_data(file, seek_head, size)?;
+    let mut entries = vec![];
+    for (seek, raw) in ebml::children(&data)?.into_iter().filter(|(e, _)| e.id == ebml::SEEK) {
+        let (mut id, mut position) = (None, None);
+        for (child, raw) in ebml::children(ebml::data(&seek, raw))? {
+            match child.id {
+                ebml::SEEK_ID => id = Some(ebml::read_uint(ebml::data(&child, raw))),
+                ebml::SEEK_POSITION => position = Some(ebml::read_uint(ebml::data(&child, raw))),
+                _ => {}
+            }
+        }
+        if let (Some(id), Some(position)) = (id, position)
+            && let Ok(id) = u32::try_from(id)
+            && matches!(id, ebml::TRACKS | ebml::TAGS | ebml::SEEK_HEAD)
+        {
+            entries.push((id, position));
+        }
+    }
+    Ok(entries)
+}
+
+/// The Void right after `element` inside the segment, if any.
+fn void_after(file: &mut File, element: &Element, segment_end: u64) -> io::Result<Option<Element>> {
+    let Some(end) = element.end().filter(|end| *end < segment_end) else {
+        return Ok(None);
+    };
+    let next = ebml::read_header(file, end)?;
+    Ok(Some(next).filter(|next| next.id == ebml::VOID && next.end().is_some_and(|end| end <= segment_end)))
+}
+
+/// Data of a Tags element with the TITLE and LANGUAGE of the edited tracks, given by UID,
+/// set to their new values, or `None` when it holds neither.
+fn retag(data: &[u8], edited: &[(u64, &TrackEdit)]) -> io::Result<Option<Vec<u8>>> {
+    let mut changed = false;
+    let mut tags = vec![];
+    for (tag, raw) in ebml::children(data)? {
+        match tag.id {
+            ebml::CRC32 => continue,
+            ebml::TAG => {}
+            _ => {
+                tags.extend_from_slice(raw);
+                continue;
+            }
+        }
+        let children = ebml::children(ebml::data(&tag, raw))?;
+        let mut uids = vec![];
+        for (targets, raw) in children.iter().filter(|(e, _)| e.id == ebml::TARGETS) {
+            uids.extend(
+                ebml::children(ebml::data(targets, raw))?
+                    .into_iter()
+                    .filter(|(target, _)| target.id == ebml::TAG_TRACK_UID)
+                    .map(|(target, raw)| ebml::read_uint(ebml::data(&target, raw))),
+            );
+        }
+        let Some(edit) = edited.iter().find(|(uid, _)| uids.contains(uid)).map(|(_, edit)| *edit) else {
+            tags.extend_from_slice(raw);
+            continue;
+        };
+
+        let mut fields = vec![];
+        let mut simple_tags = 0;
+        for (child, raw) in children {
+            match child.id {
+                ebml::CRC32 => {}
+                ebml::SIMPLE_TAG => match retag_simple(ebml::data(&child, raw), edit)? {
+                    Some(Some(simple)) => {
+                        fields.extend(simple);
+                        simple_tags += 1;
+                        changed = true;
+                    }
+                    Some(None) => changed = true,
+                    None => {
+                        fields.extend_from_slice(raw);
+                        simple_tags += 1;
+                    }
+                },
+                _ => fields.extend_from_slice(raw),
+            }
+        }
+        // A Tag without SimpleTag is invalid, the one that held only the removed title goes.
+        if simple_tags > 0 {
+            tags.extend(ebml::encode_element(ebml::TAG, &fields, None));
+        }
+    }
+    Ok(changed.then_some(tags))
+}
+
+/// SimpleTag with its value replaced, `Some(None)` when it goes with a removed title,
+/// `None` when the edit does not touch it.
+fn retag_simple(data: &[u8], edit: &TrackEdit) -> io::Result<Option<Option<Vec<u8>>>> {
+    let children = ebml::children(data)?;
+    let name = children
+        .iter()
+        .find(|(e, _)| e.id == ebml::TAG_NAME)
+        .map(|(e, raw)| String::from_utf8_lossy(ebml::data(e, raw)).to_uppercase());
+    let value = match name.as_deref() {
+        Some("TITLE") => edit.title.as_deref(),
+        Some("LANGUAGE") => edit.language.as_deref(),
+        _ => None,
+    };
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    if value.is_empty() {
+        return Ok(Some(None));
+    }
+    let mut simple = vec![];
+    for (child, raw) in children {
+        if !matches!(child.id, ebml::TAG_STRING | ebml::TAG_BINARY | ebml::CRC32) {
+            simple.extend_from_slice(raw);
+        }
+    }
+    simple.extend(ebml::encode_element(ebml::TAG_STRING, value.as_bytes(), None));
+    Ok(Some(Some(ebml::encode_element(ebml::SIMPLE_TAG, &simple, None))))
+}
+
+/// TrackEntry with the edited children replaced.
+fn edit_entry(data: &[u8], edit: &TrackEdit) -> io::Result<Vec<u8>> {
+    let replaced = |id: u32| match id {
+        ebml::LANGUAGE | ebml::LANGUAGE_BCP47 => edit.language.is_some(),
+        ebml::NAME => edit.title.is_some(),
+        ebml::FLAG_DEFAULT => edit.default.is_some(),
+        ebml::FLAG_FORCED => edit.forced.is_some(),
+        ebml::CRC32 => true,
+        _ => false,
+    };
+    let mut children = vec![];
+    for (element, raw) in ebml::children(data)? {
+        if !replaced(element.id) {
+            children.extend_from_slice(raw);
+        }
+    }
+    if let Some(language) = &edit.language {
+        // Players reading LanguageBCP47 ignore Language, older ones only know Language.
+        children.extend(ebml::encode_element(ebml::LANGUAGE, language.as_bytes(), None));
+        children.extend(ebml::encode_element(ebml::LANGUAGE_BCP47, sidecar::bcp47(language).as_bytes(), None));
+    }
+    if let Some(title) = edit.title.as_deref().filter(|t| !t.is_empty()) {
+        children.extend(ebml::encode_element(ebml::NAME, title.as_bytes(), None));
+    }
+    if let Some(default) = edit.default {
+        children.extend(ebml::encode_uint(ebml::FLAG_DEFAULT, default as u64));
+    }
+    if let Some(forced) = edit.forced {
+        children.extend(ebml::encode_uint(ebml::FLAG_FORCED, forced as u64));
+    }
+    Ok(ebml::encode_element(ebml::TRACK_ENTRY, &children, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::*;
+    use crate::matroska::ebml::{encode_element, encode_uint};
+
+    const TRACK_NUMBER: u32 = 0xD7;
+
+    #[test]
+    fn edits_tracks_into_the_following_void() {
+        let first = [encode_uint(TRACK_NUMBER, 1), encode_element(ebml::LANGUAGE, b"eng", None)].concat();
+        let second =
+            [encode_uint(TRACK_NUMBER, 2), encode_element(ebml::NAME, b"Old", None), encode_uint(ebml::FLAG_DEFAULT, 1)]
+                .concat();
+        let tracks = [encode_element(ebml::TRACK_ENTRY, &first, None), encode_element(ebml::TRACK_ENTRY, &second, None)]
+            .concat();
+        let cluster = encode_element(ebml::CLUSTER, &[0xE7, 0x81, 0x00], None);
+        // Sizes on eight bytes, as mkvmerge and ffmpeg write the Segment.
+        let segment = [encode_element(ebml::TRACKS, &tracks, Some(8)), ebml::void(40), cluster.clone()].concat();
+        let bytes = [
+            encode_element(ebml::EBML, &encode_element(0x4282, b"matroska", None), None),
+            encode_element(ebml::SEGMENT, &segment, Some(8)),
+        ]
+        .concat();
+        let path = env::temp_dir().join(format!("fmuxie-{}-edits-tracks.mkv", process::id()));
+        fs::write(&path, &bytes).unwrap();
+
+        let edit = TrackEdit {
+            stream: 1,
+            language: Some("fre".to_string()),
+            title: Some("Commentary".to_string()),
+            default: Some(false),
+            forced: None,
+        };
+        let result = edit_tracks(&path, &[edit], 2);
+        let edited = fs::read(&path).unwrap();
+        fs::remove_file(&path).ok();
+        assert_eq!(result.unwrap(), HeaderEdit::Edited);
+        assert_eq!(edited.len(), bytes.len());
+        assert!(edited.ends_with(&cluster));
+
+        let segment = ebml::parse_header(&edited[bytes.len() - segment.len() - 12..], 0).unwrap();
+        assert_eq!(segment.id, ebml::SEGMENT);
+        let children = ebml::children(&edited[bytes.len() - segment.size.unwrap() as usize..]).unwrap();
+        let ids: Vec<u32> = children.iter().map(|(e, _)| e.id).collect();
+        assert_eq!(ids, [ebml::TRACKS, ebml::VOID, ebml::CLUSTER]);
+        let (element, raw) = children[0];
+        let entries = ebml::children(ebml::data(&element, raw)).unwrap();
+        assert_eq!(entries[0].1, encode_element(ebml::TRACK_ENTRY, &first, None));
+        let fields = ebml::children(ebml::data(&entries[1].0, entries[1].1)).unwrap();
+        let value = |id: u32| fields.iter().find(|(e, _)| e.id == id).map(|(e, raw)| ebml::data(e, raw).to_vec());
+        assert_eq!(value(ebml::LANGUAGE), Some(b"fre".to_vec()));
+        assert_eq!(value(ebml::LANGUAGE_BCP47), Some(b"fr".to_vec()));
+        assert_eq!(value(ebml::NAME), Some(b"Commentary".to_vec()));
+        assert_eq!(value(ebml::FLAG_DEFAULT), Some(vec![0]));
+        assert_eq!(value(TRACK_NUMBER), Some(vec![2]));
+    }
+
+    #[test]
+    fn reports_no_room_without_padding() {
+        let entry = encode_element(ebml::TRACK_ENTRY, &encode_uint(TRACK_NUMBER, 1), None);
+        let segment = [encode_element(ebml::TRACKS, &entry, None), encode_element(ebml::CLUSTER, &[], None)].concat();
+        let bytes = [
+            encode_element(ebml::EBML, &encode_element(0x4282, b"matroska", None), None),
+            encode_element(ebml::SEGMENT, &segment, Some(8)),
+        ]
+        .concat();
+        let path = env::temp_dir().join(format!("fmuxie-{}-no-room.mkv", process::id()));
+        fs::write(&path, &bytes).unwrap();
+
+        let edit = TrackEdit { stream: 0, title: Some("A longer title".to_string()), ..TrackEdit::default() };
+        let result = edit_tracks(&path, &[edit], 1);
+        let after = fs::read(&path).unwrap();
+        fs::remove_file(&path).ok();
+        assert_eq!(result.unwrap(), HeaderEdit::NoRoom);
+        assert_eq!(after, bytes);
+    }
+
+    #[test]
+    fn refuses_a_different_track_count() {
+        let entry = encode_element(ebml::TRACK_ENTRY, &encode_uint(TRACK_NUMBER, 1), None);
+        let segment = [encode_element(ebml::TRACKS, &entry, None), ebml::void(16)].concat();
+        let bytes = [
+            encode_element(ebml::EBML, &encode_element(0x4282, b"matroska", None), None),
+            encode_element(ebml::SEGMENT, &segment, None),
+        ]
+        .concat();
+        let path = env::temp_dir().join(format!("fmuxie-{}-track-count.mkv", process::id()));
+        fs::write(&path, &bytes).unwrap();
+
+        let edit = TrackEdit { stream: 0, default: Some(true), ..TrackEdit::default() };
+        let result = edit_tracks(&path, &[edit], 2);
+        fs::remove_file(&path).ok();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn rewrites_the_tags_found_through_the_seek_head() {
+        let entry = [encode_uint(TRACK_NUMBER, 1), encode_uint(ebml::TRACK_UID, 0x5A17)].concat();
+        let tracks = encode_element(ebml::TRACKS, &encode_element(ebml::TRACK_ENTRY, &entry, None), None);
+        let simple = |name: &[u8], value: &[u8]| {
+            let fields = [encode_element(ebml::TAG_NAME, name, None), encode_element(ebml::TAG_STRING, value, None)];
+            encode_element(ebml::SIMPLE_TAG, &fields.concat(), None)
+        };
+        let targets = encode_element(ebml::TARGETS, &encode_uint(ebml::TAG_TRACK_UID, 0x5A17), None);
+        let tag = [targets, simple(b"title", b"Old"), simple(b"DURATION", b"01:00:00.000000000")].concat();
+        let tags = encode_element(ebml::TAGS, &encode_element(ebml::TAG, &tag, None), None);
+        let cluster = encode_element(ebml::CLUSTER, &[0xE7, 0x81, 0x00], None);
+        // Positions on eight bytes keep the SeekHead the same length whatever they are.
+        let seek_head = |tracks_at: usize, tags_at: usize| {
+            let seek = |id: u32, position: usize| {
+                let position = encode_element(ebml::SEEK_POSITION, &(position as u64).to_be_bytes(), None);
+                let id = encode_element(ebml::SEEK_ID, &id.to_be_bytes(), None);
+                encode_element(ebml::SEEK, &[id, position].concat(), None)
+            };
+            encode_element(ebml::SEEK_HEAD, &[seek(ebml::TRACKS, tracks_at), seek(ebml::TAGS, tags_at)].concat(), None)
+        };
+        let head_len = seek_head(0, 0).len();
+        let seek_head = seek_head(head_len, head_len + tracks.len() + 48 + cluster.len());
+
+        let cases = [
+            (true, 24, "New title", HeaderEdit::Edited, Some("New title")),
+            (true, 0, "", HeaderEdit::Edited, None),
+            (true, 0, "A much longer title", HeaderEdit::NoRoomForTags, Some("Old")),
+            // Without SeekHead the clusters are not searched for Tags.
+            (false, 24, "New title", HeaderEdit::Edited, Some("Old")),
+        ];
+        for (seekable, padding, title, expected, tagged) in cases {
+            let head = if seekable { seek_head.clone() } else { vec![] };
+            let padding = if padding > 0 { ebml::void(padding) } else { vec![] };
+            let segment =
+                [head, tracks.clone(), ebml::void(48), cluster.clone(), tags.clone(), padding.clone()].concat();
+            let bytes = [
+                encode_element(ebml::EBML, &encode_element(0x4282, b"matroska", None), None),
+                encode_element(ebml::SEGMENT, &segment, Some(8)),
+            ]
+            .concat();
+            let path = env::temp_dir().join(format!("fmuxie-{}-tags-{}.mkv", process::id(), title.len()));
+            fs::write(&path, &bytes).unwrap();
+
+            let edit = TrackEdit { stream: 0, title: Some(title.to_string()), ..TrackEdit::default() };
+            let result = edit_tracks(&path, &[edit], 1);
+            let after = fs::read(&path).unwrap();
+            fs::remove_file(&path).ok();
+            assert_eq!(result.unwrap(), expected, "{title:?}");
+            assert_eq!(after.len(), bytes.len());
+            assert_eq!(after == bytes, expected == HeaderEdit::NoRoomForTags);
+
+            let rewritten = &after[bytes.len() - tags.len() - padding.len()..];
+            let (element, raw) = ebml::children(rewritten).unwrap()[0];
+            assert_eq!(element.id, ebml::TAGS);
+            let (tag, raw) = ebml::children(ebml::data(&element, raw)).unwrap()[0];
+            let mut values = vec![];
+            for (simple, raw) in ebml::children(ebml::data(&tag, raw)).unwrap() {
+                if simple.id == ebml::SIMPLE_TAG {
+                    let fields = ebml::children(ebml::data(&simple, raw)).unwrap();
+                    let text = |(e, raw): &(Element, &[u8])| String::from_utf8_lossy(ebml::data(e, raw)).into_owned();
+                    values.push((text(&fields[0]), text(&fields[1])));
+                }
+            }
+            let title = values.iter().find(|(name, _)| name == "title").map(|(_, value)| value.as_str());
+            assert_eq!(title, tagged, "{values:?}");
+            assert!(values.contains(&("DURATION".to_string(), "01:00:00.000000000".to_string())));
+        }
+    }
+}
@@ -0,0 +1,2 @@
+pub mod ebml;
+pub mod header;
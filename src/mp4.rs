@@ -0,0 +1,121 @@
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+/// Whether the `moov` atom comes before `mdat`, so players can start before the whole file
+/// is downloaded. `None` when the file is not an ISO BMFF file.
+pub fn is_faststart(path: &Path) -> io::Result<Option<bool>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    moov_first(&mut file, len)
+}
+
+/// Walks the top-level atoms of the first `len` bytes of `file` up to `moov` or `mdat`.
+fn moov_first<R: Read + Seek>(file: &mut R, len: u64) -> io::Result<Option<bool>> {
+    let mut pos = 0;
+    let mut first = true;
+    while pos + 8 <= len {
+        let mut header = [0u8; 16];
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut header[..8])?;
+        let size = u32::from_be_bytes(header[..4].try_into().expect("4 bytes")) as u64;
+        let kind = &header[4..8];
+        if first && kind != b"ftyp" {
+            return Ok(None);
+        }
+        first = false;
+        match kind {
+            b"moov" => return Ok(Some(true)),
+            b"mdat" => return Ok(Some(false)),
+            _ => {}
+        }
+        let size = match size {
+            0 => return Ok(None),
+            1 => {
+                file.read_exact(&mut header[8..])?;
+                u64::from_be_bytes(header[8..].try_into().expect("8 bytes"))
+            }
+            size => size,
+        };
+        if size < 8 {
+            return Ok(None);
+        }
+        pos += size;
+    }
+    Ok(None)
+}
+
+pub fn is_mp4_container(container: &str) -> bool {
+    matches!(container, "mp4" | "m4v" | "m4a" | "mov")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// An atom of `kind` with a 32-bit size and `body` zero bytes.
+    fn atom(kind: &[u8; 4], body: usize) -> Vec<u8> {
+        let mut bytes = ((body + 8) as u32).to_be_bytes().to_vec();
+        bytes.extend(kind);
+        bytes.resize(body + 8, 0);
+        bytes
+    }
+
+    /// An atom of `kind` with a 64-bit `largesize` and `body` zero bytes.
+    fn large_atom(kind: &[u8; 4], body: usize) -> Vec<u8> {
+        let mut bytes = 1u32.to_be_bytes().to_vec();
+        bytes.extend(kind);
+        bytes.extend(((body + 16) as u64).to_be_bytes());
+        bytes.resize(body + 16, 0);
+        bytes
+    }
+
+    fn order(atoms: &[Vec<u8>]) -> io::Result<Option<bool>> {
+        let bytes = atoms.concat();
+        let len = bytes.len() as u64;
+        moov_first(&mut Cursor::new(bytes), len)
+    }
+
+    #[test]
+    fn finds_moov_before_or_after_mdat() {
+        let ftyp = atom(b"ftyp", 16);
+        assert_eq!(order(&[ftyp.clone(), atom(b"moov", 40), atom(b"mdat", 100)]).unwrap(), Some(true));
+        let late = [ftyp.clone(), atom(b"free", 8), atom(b"mdat", 100), atom(b"moov", 40)];
+        assert_eq!(order(&late).unwrap(), Some(false));
+        // Only files starting with ftyp are ISO BMFF.
+        assert_eq!(order(&[atom(b"moov", 40), atom(b"mdat", 100)]).unwrap(), None);
+        assert_eq!(order(&[ftyp, atom(b"free", 8)]).unwrap(), None);
+    }
+
+    #[test]
+    fn skips_64_bit_atoms() {
+        let ftyp = atom(b"ftyp", 16);
+        assert_eq!(order(&[ftyp.clone(), large_atom(b"free", 24), atom(b"moov", 40)]).unwrap(), Some(true));
+        assert_eq!(order(&[ftyp, large_atom(b"mdat", 100), atom(b"moov", 40)]).unwrap(), Some(false));
+    }
+
+    #[test]
+    fn stops_at_truncated_and_bad_sizes() {
+        let ftyp = atom(b"ftyp", 16);
+        // An atom running past the end of the file hides whatever follows it.
+        let mut cut = atom(b"free", 64);
+        cut.truncate(20);
+        assert_eq!(order(&[ftyp.clone(), cut]).unwrap(), None);
+        // A largesize cut short.
+        let mut large = large_atom(b"free", 8);
+        large.truncate(12);
+        assert!(order(&[ftyp.clone(), large]).is_err());
+        // Sizes below the header, and size 0 which runs to the end of the file.
+        let mut tiny = atom(b"free", 8);
+        tiny[3] = 4;
+        assert_eq!(order(&[ftyp.clone(), tiny, atom(b"moov", 40)]).unwrap(), None);
+        let mut rest = atom(b"free", 8);
+        rest[3] = 0;
+        assert_eq!(order(&[ftyp, rest, atom(b"moov", 40)]).unwrap(), None);
+        assert_eq!(order(&[]).unwrap(), None);
+    }
+}
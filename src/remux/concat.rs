@@ -0,0 +1,80 @@
+use std::path::{Path, PathBuf};
+
+use crate::ffmpeg::probe::{ProbeInfo, StreamInfo};
+
+use super::plan::{Chapter, RemuxPlan, Segment};
+
+/// Differences between the parts that would break a stream copy concat.
+pub fn check_compatible(parts: &[(PathBuf, ProbeInfo)]) -> Vec<String> {
+    let Some((first_path, first)) = parts.first() else {
+        return vec![];
+    };
+    let mut mismatches = vec![];
+    for (path, probe) in &parts[1..] {
+        let name = file_name(path);
+        if probe.streams.len() != first.streams.len() {
+            mismatches.push(format!(
+                "{name}: {} streams, {} has {}",
+                probe.streams.len(),
+                file_name(first_path),
+                first.streams.len()
+            ));
+            continue;
+        }
+        for (a, b) in first.streams.iter().zip(&probe.streams) {
+            if let Some(difference) = stream_difference(a, b) {
+                mismatches.push(format!("{name}: stream {} {difference}", b.index));
+            }
+        }
+    }
+    mismatches
+}
+
+fn stream_difference(a: &StreamInfo, b: &StreamInfo) -> Option<String> {
+    let differs = |what: &str, x: String, y: String| (x != y).then(|| format!("{what} {y} instead of {x}"));
+    differs("type", a.codec_type().to_string(), b.codec_type().to_string())
+        .or_else(|| differs("codec", a.codec_name().to_string(), b.codec_name().to_string()))
+        .or_else(|| differs("resolution", resolution(a), resolution(b)))
+        .or_else(|| differs("sample rate", opt(&a.sample_rate), opt(&b.sample_rate)))
+        .or_else(|| differs("channels", opt(&a.channels), opt(&b.channels)))
+        .or_else(|| differs("channel layout", opt(&a.channel_layout), opt(&b.channel_layout)))
+}
+
+fn resolution(stream: &StreamInfo) -> String {
+    match (stream.width, stream.height) {
+        (Some(w), Some(h)) => format!("{w}x{h}"),
+        _ => "-".to_string(),
+    }
+}
+
+fn opt<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map(T::to_string).unwrap_or_else(|| "-".to_string())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+/// Joins the parts in order into `output`, optionally with one chapter per part.
+pub fn plan(parts: Vec<(PathBuf, ProbeInfo)>, output: PathBuf, chapters: bool) -> RemuxPlan {
+    let warnings = check_compatible(&parts);
+    let (first_path, first) = parts[0].clone();
+    let mut plan = RemuxPlan::new(first_path, output, first);
+    plan.warnings = warnings;
+
+    let mut start = 0.0;
+    for (path, probe) in parts {
+        let duration = probe.duration();
+        if chapters {
+            let end = start + duration.unwrap_or(0.0);
+            plan.chapters.push(Chapter {
+                start,
+                end,
+                title: path.file_stem().unwrap_or_default().to_string_lossy().into_owned(),
+            });
+            start = end;
+        }
+        plan.segments.push(Segment { path, duration, inpoint: None, outpoint: None });
+    }
+    plan
+}
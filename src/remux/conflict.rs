@@ -0,0 +1,222 @@
+use std::{
+    fs,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::plan::RemuxPlan;
+
+/// What to do when the output path of a job already exists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConflictPolicy {
+    #[default]
+    Ask,
+    Skip,
+    Overwrite,
+    AutoSuffix,
+}
+
+impl ConflictPolicy {
+    pub fn next(self) -> Self {
+        match self {
+            ConflictPolicy::Ask => ConflictPolicy::Skip,
+            ConflictPolicy::Skip => ConflictPolicy::Overwrite,
+            ConflictPolicy::Overwrite => ConflictPolicy::AutoSuffix,
+            ConflictPolicy::AutoSuffix => ConflictPolicy::Ask,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ConflictPolicy::Ask => "ask",
+            ConflictPolicy::Skip => "skip",
+            ConflictPolicy::Overwrite => "overwrite",
+            ConflictPolicy::AutoSuffix => "auto-suffix",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conflict {
+    /// The plan can run as is.
+    None,
+    Skip,
+    /// The user has to decide between overwriting and skipping.
+    Ask,
+}
+
+/// Applies the policy to a plan whose output may already exist. Writing onto the input
+/// file is refused here, in-place remuxes go through a temporary file instead.
+pub fn apply(plan: &mut RemuxPlan, policy: ConflictPolicy) -> Result<Conflict, String> {
+    if same_file(&plan.input, &plan.output) {
+        return Err(format!("output {} is the input file, use in-place mode (i) to replace it", plan.output.display()));
+    }
+    if plan.in_place {
+        // Only a leftover of an interrupted in-place remux can be there.
+        plan.overwrite = true;
+        return Ok(Conflict::None);
+    }
+    plan.overwrite = false;
+    if !plan.first_output_file().exists() {
+        return Ok(Conflict::None);
+    }
+    match policy {
+        ConflictPolicy::Ask => Ok(Conflict::Ask),
+        ConflictPolicy::Skip => Ok(Conflict::Skip),
+        ConflictPolicy::Overwrite => {
+            plan.overwrite = true;
+            Ok(Conflict::None)
+        }
+        ConflictPolicy::AutoSuffix => {
+            let base = plan.output.clone();
+            for n in 1.. {
+                plan.output = suffixed(&base, n);
+                if !plan.first_output_file().exists() {
+                    break;
+                }
+            }
+            Ok(Conflict::None)
+        }
+    }
+}
+
+/// First `name (n).ext` that does not exist yet.
+pub fn next_free_path(path: &Path) -> PathBuf {
+    next_path_besides(path, &[])
+}
+
+/// First `name (n).ext` that neither exists nor is among the paths `taken` by other outputs.
+pub fn next_path_besides(path: &Path, taken: &[&Path]) -> PathBuf {
+    (1..)
+        .map(|n| suffixed(path, n))
+        .find(|candidate| !candidate.exists() && !taken.contains(&candidate.as_path()))
+        .expect("unbounded range")
+}
+
+/// The path itself when free, otherwise the first free `name (n).ext`.
+pub fn free_path(path: &Path) -> PathBuf {
+    if path.exists() { next_free_path(path) } else { path.to_path_buf() }
+}
+
+fn suffixed(path: &Path, n: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    path.with_file_name(format!("{stem} ({n}){ext}"))
+}
+
+pub fn same_file(a: &Path, b: &Path) -> bool {
+    if let (Ok(a), Ok(b)) = (fs::metadata(a), fs::metadata(b)) {
+        return a.dev() == b.dev() && a.ino() == b.ino();
+    }
+    let canonical = |p: &Path| {
+        let parent = p.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        parent.canonicalize().ok().map(|dir| dir.join(p.file_name().unwrap_or_default()))
+    };
+    matches!((canonical(a), canonical(b)), (Some(a), Some(b)) if a == b)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+    use crate::ffmpeg::probe::ProbeInfo;
+
+    /// A directory holding `in.mkv`, `out.mkv` and `out (1).mkv`.
+    fn setup(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("fmuxie-{}-{name}", process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        for file in ["in.mkv", "out.mkv", "out (1).mkv"] {
+            fs::write(dir.join(file), file).unwrap();
+        }
+        dir
+    }
+
+    fn plan(dir: &Path, output: &str) -> RemuxPlan {
+        RemuxPlan::new(dir.join("in.mkv"), dir.join(output), ProbeInfo::default())
+    }
+
+    #[test]
+    fn applies_each_policy_to_an_existing_output() {
+        let dir = setup("policies");
+        let apply_to = |output: &str, policy| {
+            let mut plan = plan(&dir, output);
+            apply(&mut plan, policy).map(|conflict| (conflict, plan.output, plan.overwrite))
+        };
+        let free = apply_to("new.mkv", ConflictPolicy::Ask);
+        let ask = apply_to("out.mkv", ConflictPolicy::Ask);
+        let skip = apply_to("out.mkv", ConflictPolicy::Skip);
+        let overwrite = apply_to("out.mkv", ConflictPolicy::Overwrite);
+        let suffixed = apply_to("out.mkv", ConflictPolicy::AutoSuffix);
+        fs::remove_dir_all(&dir).ok();
+        assert_eq!(free, Ok((Conflict::None, dir.join("new.mkv"), false)));
+        assert_eq!(ask, Ok((Conflict::Ask, dir.join("out.mkv"), false)));
+        assert_eq!(skip, Ok((Conflict::Skip, dir.join("out.mkv"), false)));
+        assert_eq!(overwrite, Ok((Conflict::None, dir.join("out.mkv"), true)));
+        assert_eq!(suffixed, Ok((Conflict::None, dir.join("out (2).mkv"), false)));
+    }
+
+    #[test]
+    fn refuses_to_write_onto_the_input() {
+        let dir = setup("onto-input");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        let mut same = plan(&dir, "in.mkv");
+        let same = apply(&mut same, ConflictPolicy::Overwrite);
+        // The same file reached through another path, and through a hard link.
+        let mut around = plan(&dir, "sub/../in.mkv");
+        let around = apply(&mut around, ConflictPolicy::Overwrite);
+        fs::hard_link(dir.join("in.mkv"), dir.join("link.mkv")).unwrap();
+        let mut linked = plan(&dir, "link.mkv");
+        let linked = apply(&mut linked, ConflictPolicy::AutoSuffix);
+        let mut in_place = plan(&dir, "out.mkv");
+        in_place.in_place = true;
+        let replaced = apply(&mut in_place, ConflictPolicy::Skip);
+        fs::remove_dir_all(&dir).ok();
+        assert!(same.unwrap_err().contains("is the input file"));
+        assert!(around.is_err());
+        assert!(linked.is_err());
+        // The temp file of an in-place remux is only ever a leftover.
+        assert_eq!(replaced, Ok(Conflict::None));
+        assert!(in_place.overwrite);
+    }
+
+    #[test]
+    fn numbers_free_paths() {
+        let dir = setup("numbers");
+        let next = next_free_path(&dir.join("out.mkv"));
+        let free = free_path(&dir.join("out.mkv"));
+        let unused = free_path(&dir.join("new.mkv"));
+        let besides = next_path_besides(&dir.join("out.mkv"), &[&dir.join("out (2).mkv")]);
+        let bare = next_free_path(&dir.join("in"));
+        fs::remove_dir_all(&dir).ok();
+        assert_eq!(next, dir.join("out (2).mkv"));
+        assert_eq!(free, dir.join("out (2).mkv"));
+        assert_eq!(unused, dir.join("new.mkv"));
+        assert_eq!(besides, dir.join("out (3).mkv"));
+        assert_eq!(bare, dir.join("in (1)"));
+    }
+
+    #[test]
+    fn recognises_the_same_file() {
+        let dir = setup("same-file");
+        fs::hard_link(dir.join("in.mkv"), dir.join("link.mkv")).unwrap();
+        let linked = same_file(&dir.join("in.mkv"), &dir.join("link.mkv"));
+        let copy = same_file(&dir.join("in.mkv"), &dir.join("out.mkv"));
+        // Files that do not exist yet compare by their resolved directory.
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        let missing = same_file(&dir.join("new.mkv"), &dir.join("sub/../new.mkv"));
+        let different = same_file(&dir.join("new.mkv"), &dir.join("other.mkv"));
+        let absolute = env::current_dir().unwrap().join("fmuxie-missing.mkv");
+        let relative = same_file(Path::new("fmuxie-missing.mkv"), &absolute);
+        fs::remove_dir_all(&dir).ok();
+        assert!(linked);
+        assert!(!copy);
+        assert!(missing);
+        assert!(!different);
+        assert!(relative);
+    }
+}
@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+
+use crate::ffmpeg::probe::StreamInfo;
+
+/// Header properties of one stream to change, `None` leaving them as they are.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrackEdit {
+    /// Index of the stream in the input.
+    pub stream: usize,
+    pub language: Option<String>,
+    /// An empty title removes it.
+    pub title: Option<String>,
+    pub default: Option<bool>,
+    pub forced: Option<bool>,
+}
+
+impl TrackEdit {
+    /// Parses `language=fre; title=Commentary; default=no; forced=yes`, any part being optional.
+    pub fn parse(stream: usize, text: &str) -> Result<TrackEdit, String> {
+        let mut edit = TrackEdit { stream, ..TrackEdit::default() };
+        for part in text.split(';').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = part.split_once('=').ok_or_else(|| format!("expected key=value in \"{part}\""))?;
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "language" | "lang" => {
+                    if !(value.len() == 3 && value.chars().all(|c| c.is_ascii_lowercase())) {
+                        return Err(format!("\"{value}\" is not a 3-letter ISO 639-2 code"));
+                    }
+                    edit.language = Some(value.to_string());
+                }
+                "title" | "name" => edit.title = Some(value.to_string()),
+                "default" => edit.default = Some(parse_flag(value)?),
+                "forced" => edit.forced = Some(parse_flag(value)?),
+                other => return Err(format!("unknown property \"{other}\"")),
+            }
+        }
+        Ok(edit)
+    }
+
+    /// Current properties of a stream in the form accepted by `parse`.
+    pub fn spec_of(stream: &StreamInfo) -> String {
+        let flag = |value: u8| if value == 1 { "yes" } else { "no" };
+        format!(
+            "language={}; title={}; default={}; forced={}",
+            stream.language().unwrap_or("und"),
+            stream.title().unwrap_or_default(),
+            flag(stream.disposition.default),
+            flag(stream.disposition.forced)
+        )
+    }
+
+    /// Whether the edit changes anything on `stream`.
+    pub fn changes(&self, stream: &StreamInfo) -> bool {
+        self.language.as_deref().is_some_and(|l| Some(l) != stream.language() && !(l == "und" && stream.language().is_none()))
+            || self.title.as_deref().is_some_and(|t| t != stream.title().unwrap_or_default())
+            || self.default.is_some_and(|d| d != (stream.disposition.default == 1))
+            || self.forced.is_some_and(|f| f != (stream.disposition.forced == 1))
+    }
+
+    /// Disposition of `stream` once the flags of the edit are applied, as ffmpeg expects it.
+    pub fn disposition(&self, stream: &StreamInfo) -> String {
+        let current = &stream.disposition;
+        let flags = [
+            ("default", self.default.unwrap_or(current.default == 1)),
+            ("forced", self.forced.unwrap_or(current.forced == 1)),
+            ("comment", current.comment == 1),
+            ("hearing_impaired", current.hearing_impaired == 1),
+            ("attached_pic", current.attached_pic == 1),
+        ];
+        let set: Vec<&str> = flags.iter().filter(|(_, on)| *on).map(|(name, _)| *name).collect();
+        if set.is_empty() { "0".to_string() } else { set.join("+") }
+    }
+}
+
+fn parse_flag(value: &str) -> Result<bool, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "yes" | "y" | "1" | "true" | "on" => Ok(true),
+        "no" | "n" | "0" | "false" | "off" => Ok(false),
+        _ => Err(format!("\"{value}\" is not yes or no")),
+    }
+}
@@ -0,0 +1,203 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ffmpeg::probe::{ProbeInfo, StreamInfo};
+
+use super::{
+    conflict::{self, Conflict, ConflictPolicy},
+    template,
+};
+
+/// One stream written to its own file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractOutput {
+    pub stream: usize,
+    pub path: PathBuf,
+    pub format: String,
+    pub codec: String,
+    pub bitstream_filter: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractPlan {
+    pub input: PathBuf,
+    pub duration: Option<f64>,
+    pub outputs: Vec<ExtractOutput>,
+    pub overwrite: bool,
+}
+
+/// File extension, muxer, codec and bitstream filter used to extract a stream.
+/// Video is written as a raw Annex B elementary stream when possible.
+fn target(stream: &StreamInfo) -> Option<(&'static str, &'static str, &'static str, Option<&'static str>)> {
+    let target = match (stream.codec_type(), stream.codec_name()) {
+        ("subtitle", "subrip") => ("srt", "srt", "copy", None),
+        ("subtitle", "ass" | "ssa") => ("ass", "ass", "copy", None),
+        ("subtitle", "webvtt") => ("vtt", "webvtt", "copy", None),
+        ("subtitle", "mov_text") => ("srt", "srt", "srt", None),
+        ("subtitle", "hdmv_pgs_subtitle") => ("sup", "sup", "copy", None),
+        ("subtitle", _) => ("mks", "matroska", "copy", None),
+        ("audio", "ac3") => ("ac3", "ac3", "copy", None),
+        ("audio", "eac3") => ("eac3", "eac3", "copy", None),
+        ("audio", "dts") => ("dts", "dts", "copy", None),
+        ("audio", "truehd") => ("thd", "truehd", "copy", None),
+        ("audio", "flac") => ("flac", "flac", "copy", None),
+        ("audio", "aac") => ("aac", "adts", "copy", None),
+        ("audio", "mp3") => ("mp3", "mp3", "copy", None),
+        ("audio", "opus") => ("opus", "opus", "copy", None),
+        ("audio", _) => ("mka", "matroska", "copy", None),
+        ("video", "h264") => ("h264", "h264", "copy", Some("h264_mp4toannexb")),
+        ("video", "hevc") => ("hevc", "hevc", "copy", Some("hevc_mp4toannexb")),
+        ("video", _) => ("mkv", "matroska", "copy", None),
+        _ => return None,
+    };
+    Some(target)
+}
+
+/// Plans the extraction of the given stream indexes, naming each file with `name_template`.
+pub fn plan(
+    input: &Path,
+    probe: &ProbeInfo,
+    streams: &[usize],
+    name_template: &str,
+    policy: ConflictPolicy,
+) -> Result<(ExtractPlan, Conflict), String> {
+    let mut extract = ExtractPlan {
+        input: input.to_path_buf(),
+        duration: probe.duration(),
+        outputs: vec![],
+        overwrite: policy == ConflictPolicy::Overwrite,
+    };
+    let mut conflict = Conflict::None;
+
+    for &index in streams {
+        let stream = probe
+            .streams
+            .iter()
+            .find(|s| s.index == index)
+            .ok_or_else(|| format!("stream {index} not found"))?;
+        let (ext, format, codec, bitstream_filter) = target(stream)
+            .ok_or_else(|| format!("stream {index} ({}) cannot be extracted", stream.codec_type()))?;
+        let mut path = template::render_stream(name_template, input, probe, stream, ext)?;
+
+        if conflict::same_file(input, &path) {
+            return Err(format!("output {} is the input file", path.display()));
+        }
+        // A template without `{index}` can give two streams one name, the later stream is
+        // then handled as if the file of the earlier one existed.
+        let planned = extract.outputs.iter().any(|o| o.path == path);
+        if path.exists() || planned {
+            match policy {
+                ConflictPolicy::Skip => continue,
+                ConflictPolicy::AutoSuffix => {
+                    let taken: Vec<&Path> = extract.outputs.iter().map(|o| o.path.as_path()).collect();
+                    path = conflict::next_path_besides(&path, &taken);
+                }
+                ConflictPolicy::Ask => conflict = Conflict::Ask,
+                ConflictPolicy::Overwrite => {}
+            }
+        }
+        // Two muxers cannot write one file, the later stream takes it.
+        extract.outputs.retain(|o| o.path != path);
+        extract.outputs.push(ExtractOutput {
+            stream: index,
+            path,
+            format: format.to_string(),
+            codec: codec.to_string(),
+            bitstream_filter: bitstream_filter.map(str::to_string),
+        });
+    }
+
+    if extract.outputs.is_empty() {
+        conflict = Conflict::Skip;
+    }
+    Ok((extract, conflict))
+}
+
+impl ExtractPlan {
+    /// Arguments passed to ffmpeg after the global options; every stream is one output.
+    pub fn ffmpeg_args(&self) -> Vec<String> {
+        let mut args = vec![
+            if self.overwrite { "-y" } else { "-n" }.to_string(),
+            "-i".to_string(),
+            self.input.to_string_lossy().into_owned(),
+        ];
+        for output in &self.outputs {
+            args.extend(["-map".to_string(), format!("0:{}", output.stream)]);
+            args.extend(["-c".to_string(), output.codec.clone()]);
+            if let Some(filter) = &output.bitstream_filter {
+                args.extend(["-bsf".to_string(), filter.clone()]);
+            }
+            args.extend(["-f".to_string(), output.format.clone()]);
+            args.push(output.path.to_string_lossy().into_owned());
+        }
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROBE: &str = r#"{"streams": [
+        {"index": 0, "codec_type": "video", "codec_name": "hevc"},
+        {"index": 1, "codec_type": "audio", "codec_name": "pcm_s24le", "tags": {"language": "jpn"}},
+        {"index": 2, "codec_type": "subtitle", "codec_name": "hdmv_pgs_subtitle", "tags": {"language": "eng"}},
+        {"index": 3, "codec_type": "subtitle", "codec_name": "subrip", "tags": {"language": "eng"}},
+        {"index": 4, "codec_type": "subtitle", "codec_name": "subrip", "tags": {"language": "eng", "title": "SDH"}},
+        {"index": 5, "codec_type": "video", "codec_name": "h264"},
+        {"index": 6, "codec_type": "subtitle", "codec_name": "mov_text"},
+        {"index": 7, "codec_type": "attachment", "codec_name": "ttf"}
+    ]}"#;
+
+    #[test]
+    fn picks_a_format_for_each_codec() {
+        let probe: ProbeInfo = serde_json::from_str(PROBE).unwrap();
+        let targets: Vec<_> = probe.streams.iter().map(target).collect();
+        assert_eq!(targets[0], Some(("hevc", "hevc", "copy", Some("hevc_mp4toannexb"))));
+        assert_eq!(targets[1], Some(("mka", "matroska", "copy", None)));
+        assert_eq!(targets[2], Some(("sup", "sup", "copy", None)));
+        assert_eq!(targets[3], Some(("srt", "srt", "copy", None)));
+        assert_eq!(targets[5], Some(("h264", "h264", "copy", Some("h264_mp4toannexb"))));
+        // MP4 text subtitles have no file format of their own.
+        assert_eq!(targets[6], Some(("srt", "srt", "srt", None)));
+        assert_eq!(targets[7], None);
+    }
+
+    #[test]
+    fn writes_each_stream_to_its_own_file() {
+        let probe: ProbeInfo = serde_json::from_str(PROBE).unwrap();
+        let input = Path::new("/nonexistent/Movie.mkv");
+        let template = "{dir}/{stem}.track{index}.{ext}";
+        let (extract, conflict) = plan(input, &probe, &[0, 2], template, ConflictPolicy::Ask).unwrap();
+        assert_eq!(conflict, Conflict::None);
+        assert_eq!(
+            extract.ffmpeg_args().join(" "),
+            "-n -i /nonexistent/Movie.mkv \
+             -map 0:0 -c copy -bsf hevc_mp4toannexb -f hevc /nonexistent/Movie.track0.hevc \
+             -map 0:2 -c copy -f sup /nonexistent/Movie.track2.sup"
+        );
+        assert!(plan(input, &probe, &[7], template, ConflictPolicy::Ask).unwrap_err().contains("cannot be extracted"));
+        assert!(plan(input, &probe, &[9], template, ConflictPolicy::Ask).unwrap_err().contains("not found"));
+    }
+
+    #[test]
+    fn streams_sharing_a_name_follow_the_conflict_policy() {
+        let probe: ProbeInfo = serde_json::from_str(PROBE).unwrap();
+        let input = Path::new("/nonexistent/Movie.mkv");
+        let outputs = |policy| {
+            let (extract, conflict) = plan(input, &probe, &[3, 4], "{dir}/{stem}.{lang}.{ext}", policy).unwrap();
+            let outputs: Vec<(usize, String)> =
+                extract.outputs.iter().map(|o| (o.stream, o.path.display().to_string())).collect();
+            (outputs, conflict)
+        };
+        let path = "/nonexistent/Movie.eng.srt".to_string();
+        assert_eq!(
+            outputs(ConflictPolicy::AutoSuffix),
+            (vec![(3, path.clone()), (4, "/nonexistent/Movie.eng (1).srt".to_string())], Conflict::None)
+        );
+        assert_eq!(outputs(ConflictPolicy::Skip), (vec![(3, path.clone())], Conflict::None));
+        assert_eq!(outputs(ConflictPolicy::Overwrite), (vec![(4, path.clone())], Conflict::None));
+        assert_eq!(outputs(ConflictPolicy::Ask), (vec![(4, path)], Conflict::Ask));
+    }
+}
@@ -0,0 +1,188 @@
+use std::{
+    fs::{self, File, FileTimes},
+    io,
+    os::unix::fs::{MetadataExt, chown},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::conflict;
+
+/// What happens to the original file once an in-place remux replaced it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Backup {
+    None,
+    /// Keep it next to the new file as `name.ext.bak`.
+    #[default]
+    Bak,
+    /// Move it to the configured backup folder.
+    Folder,
+}
+
+impl Backup {
+    pub fn label(self) -> &'static str {
+        match self {
+            Backup::None => "no backup",
+            Backup::Bak => "keep .bak",
+            Backup::Folder => "move to backup folder",
+        }
+    }
+}
+
+/// Hidden file next to the input, so the final rename stays on one filesystem.
+pub fn temp_output(input: &Path) -> PathBuf {
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = input.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    input.with_file_name(format!(".{stem}.fmuxie-tmp{ext}"))
+}
+
+/// Directory the backup of `original` goes to, `None` when none is kept.
+pub fn backup_dir(original: &Path, backup: Backup, backup_dir: &Path) -> Option<PathBuf> {
+    match backup {
+        Backup::None => None,
+        Backup::Bak => original.parent().map(Path::to_path_buf),
+        Backup::Folder => Some(backup_dir.to_path_buf()).filter(|dir| !dir.as_os_str().is_empty()),
+    }
+}
+
+/// Gives `temp` the permissions, ownership and times of `original`, keeps a backup of the
+/// original if asked, then renames `temp` over it. Returns where the backup went.
+pub fn replace(original: &Path, temp: &Path, backup: Backup, backup_dir: &Path) -> io::Result<Option<PathBuf>> {
+    let metadata = fs::metadata(original)?;
+    fs::set_permissions(temp, metadata.permissions())?;
+    if let Err(err) = chown(temp, Some(metadata.uid()), Some(metadata.gid()))
+        && fs::metadata(temp)?.uid() != metadata.uid()
+    {
+        return Err(io::Error::new(err.kind(), format!("cannot keep the owner: {err}")));
+    }
+    let times = FileTimes::new().set_accessed(metadata.accessed()?).set_modified(metadata.modified()?);
+    File::options().write(true).open(temp)?.set_times(times)?;
+
+    let name = original.file_name().unwrap_or_default().to_string_lossy().into_owned();
+    let backup_path = match backup {
+        Backup::None => None,
+        Backup::Bak => Some(conflict::free_path(&original.with_file_name(format!("{name}.bak")))),
+        Backup::Folder => {
+            if backup_dir.as_os_str().is_empty() {
+                return Err(io::Error::other("backup_dir is not set"));
+            }
+            fs::create_dir_all(backup_dir)?;
+            Some(conflict::free_path(&backup_dir.join(&name)))
+        }
+    };
+    if let Some(path) = &backup_path {
+        // A hard link keeps the original in place until the rename swaps it.
+        if fs::hard_link(original, path).is_err() {
+            fs::copy(original, path)?;
+            File::options().write(true).open(path)?.set_times(times)?;
+        }
+    }
+    fs::rename(temp, original)?;
+    Ok(backup_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        os::unix::fs::PermissionsExt,
+        process,
+        time::{Duration, SystemTime},
+    };
+
+    use super::*;
+
+    /// A directory holding `Film.mkv` with old times and 0640 permissions, and its remuxed temp file.
+    fn setup(name: &str) -> (PathBuf, PathBuf, PathBuf) {
+        let dir = env::temp_dir().join(format!("fmuxie-{}-{name}", process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        let original = dir.join("Film.mkv");
+        fs::write(&original, "original").unwrap();
+        fs::set_permissions(&original, fs::Permissions::from_mode(0o640)).unwrap();
+        let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        File::options().write(true).open(&original).unwrap().set_times(FileTimes::new().set_modified(old)).unwrap();
+        let temp = temp_output(&original);
+        fs::write(&temp, "remuxed").unwrap();
+        (dir, original, temp)
+    }
+
+    #[test]
+    fn names_the_temp_file_after_the_input() {
+        assert_eq!(temp_output(Path::new("/films/Film.mkv")), Path::new("/films/.Film.fmuxie-tmp.mkv"));
+        assert_eq!(temp_output(Path::new("/films/Film")), Path::new("/films/.Film.fmuxie-tmp"));
+        let original = Path::new("/films/Film.mkv");
+        assert_eq!(backup_dir(original, Backup::Bak, Path::new("")), Some(PathBuf::from("/films")));
+        assert_eq!(backup_dir(original, Backup::Folder, Path::new("/backup")), Some(PathBuf::from("/backup")));
+        assert_eq!(backup_dir(original, Backup::Folder, Path::new("")), None);
+        assert_eq!(backup_dir(original, Backup::None, Path::new("/backup")), None);
+    }
+
+    #[test]
+    fn swaps_in_the_new_file_with_the_old_times_and_mode() {
+        let (dir, original, temp) = setup("swap");
+        let before = fs::metadata(&original).unwrap();
+        let backup = replace(&original, &temp, Backup::None, Path::new("")).unwrap();
+        let after = fs::metadata(&original).unwrap();
+        let content = fs::read_to_string(&original).unwrap();
+        let left: Vec<_> = fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+        fs::remove_dir_all(&dir).ok();
+        assert_eq!(backup, None);
+        assert_eq!(content, "remuxed");
+        assert_eq!(after.modified().unwrap(), before.modified().unwrap());
+        assert_eq!(after.permissions().mode() & 0o777, 0o640);
+        assert_eq!(after.uid(), before.uid());
+        assert_eq!(left, ["Film.mkv"]);
+    }
+
+    #[test]
+    fn keeps_the_original_as_bak() {
+        let (dir, original, temp) = setup("bak");
+        let backup = replace(&original, &temp, Backup::Bak, Path::new("")).unwrap();
+        let kept = fs::read_to_string(dir.join("Film.mkv.bak")).unwrap();
+        // A second remux keeps the first backup.
+        fs::write(&temp, "remuxed again").unwrap();
+        let second = replace(&original, &temp, Backup::Bak, Path::new("")).unwrap();
+        let first_kept = fs::read_to_string(dir.join("Film.mkv.bak")).unwrap();
+        let second_kept = fs::read_to_string(dir.join("Film.mkv (1).bak")).unwrap();
+        let content = fs::read_to_string(&original).unwrap();
+        let modified = fs::metadata(dir.join("Film.mkv.bak")).unwrap().modified().unwrap();
+        fs::remove_dir_all(&dir).ok();
+        assert_eq!(backup, Some(dir.join("Film.mkv.bak")));
+        assert_eq!(second, Some(dir.join("Film.mkv (1).bak")));
+        assert_eq!(kept, "original");
+        assert_eq!(first_kept, "original");
+        assert_eq!(second_kept, "remuxed");
+        assert_eq!(content, "remuxed again");
+        assert_eq!(modified, SystemTime::UNIX_EPOCH + Duration::from_secs(1_500_000_000));
+    }
+
+    #[test]
+    fn moves_the_original_to_the_backup_folder() {
+        let (dir, original, temp) = setup("folder");
+        let folder = dir.join("backup/films");
+        let backup = replace(&original, &temp, Backup::Folder, &folder).unwrap();
+        let kept = fs::read_to_string(folder.join("Film.mkv")).unwrap();
+        let content = fs::read_to_string(&original).unwrap();
+        let bak = dir.join("Film.mkv.bak").exists();
+        fs::remove_dir_all(&dir).ok();
+        assert_eq!(backup, Some(folder.join("Film.mkv")));
+        assert_eq!(kept, "original");
+        assert_eq!(content, "remuxed");
+        assert!(!bak);
+    }
+
+    #[test]
+    fn leaves_the_original_alone_without_a_backup_folder() {
+        let (dir, original, temp) = setup("no-folder");
+        let err = replace(&original, &temp, Backup::Folder, Path::new("")).unwrap_err();
+        let content = fs::read_to_string(&original).unwrap();
+        let temp_left = temp.exists();
+        fs::remove_dir_all(&dir).ok();
+        assert_eq!(err.to_string(), "backup_dir is not set");
+        assert_eq!(content, "original");
+        assert!(temp_left);
+    }
+}
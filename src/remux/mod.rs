@@ -0,0 +1,14 @@
+pub mod concat;
+pub mod conflict;
+pub mod edit;
+pub mod extract;
+pub mod in_place;
+pub mod plan;
+pub mod preflight;
+pub mod rules;
+pub mod sidecar;
+pub mod split;
+pub mod template;
+pub mod transcode;
+pub mod trim;
+pub mod verify;
@@ -0,0 +1,377 @@
+use std::{env, path::PathBuf, process};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ffmpeg::probe::ProbeInfo, mp4};
+
+use super::{
+    conflict::ConflictPolicy,
+    edit::TrackEdit,
+    sidecar::Sidecar,
+    split::{self, SplitMode},
+    template,
+    transcode::{self, AudioTranscode, SubtitleAction, SubtitleFormat},
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemuxPlan {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub source: ProbeInfo,
+    /// Parts of files to join instead of reading `input` whole, through the concat demuxer.
+    pub segments: Vec<Segment>,
+    /// Extra subtitle/audio files muxed after the streams of the input.
+    pub sidecars: Vec<Sidecar>,
+    /// Replaces the chapters of the input when not empty.
+    pub chapters: Vec<Chapter>,
+    /// Audio streams re-encoded instead of copied.
+    pub audio: Vec<AudioTranscode>,
+    /// Language, title and flag changes of input streams.
+    pub edits: Vec<TrackEdit>,
+    /// Indexes of input streams left out of the output.
+    #[serde(default)]
+    pub dropped: Vec<usize>,
+    /// Text subtitle format to convert to; subtitles the container cannot hold are converted anyway.
+    pub subtitles: Option<SubtitleFormat>,
+    /// Problems found while planning, shown before running.
+    pub warnings: Vec<String>,
+    pub split: Option<SplitMode>,
+    split_args: Vec<String>,
+    /// Adds `-movflags +faststart` when the output is an MP4.
+    pub faststart: bool,
+    /// `output` is a temporary file that replaces `input` once verified.
+    pub in_place: bool,
+    /// Overrides the configured policy for this job only.
+    pub conflict_policy: Option<ConflictPolicy>,
+    pub overwrite: bool,
+    /// Queue job running the plan, which keeps its temporary files apart from other jobs'.
+    #[serde(default)]
+    pub job: Option<u64>,
+}
+
+/// One entry of an ffconcat list.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Segment {
+    pub path: PathBuf,
+    /// Duration of the whole file, used to estimate progress.
+    pub duration: Option<f64>,
+    pub inpoint: Option<f64>,
+    pub outpoint: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chapter {
+    pub start: f64,
+    pub end: f64,
+    pub title: String,
+}
+
+/// A stream of the output taken from the input, in output order.
+#[derive(Debug, Clone)]
+pub struct OutputStream {
+    /// Index of the stream in the input.
+    pub source: usize,
+    pub encoding: Encoding,
+}
+
+#[derive(Debug, Clone)]
+pub enum Encoding {
+    Copy,
+    Audio(AudioTranscode),
+    Subtitle(SubtitleFormat),
+}
+
+impl Segment {
+    pub fn length(&self) -> Option<f64> {
+        let end = self.outpoint.or(self.duration)?;
+        Some(end - self.inpoint.unwrap_or(0.0))
+    }
+}
+
+impl RemuxPlan {
+    pub fn new(input: PathBuf, output: PathBuf, source: ProbeInfo) -> Self {
+        Self {
+            input,
+            output,
+            source,
+            segments: vec![],
+            sidecars: vec![],
+            chapters: vec![],
+            audio: vec![],
+            subtitles: None,
+            edits: vec![],
+            dropped: vec![],
+            warnings: vec![],
+            split: None,
+            split_args: vec![],
+            faststart: false,
+            in_place: false,
+            conflict_policy: None,
+            overwrite: false,
+            job: None,
+        }
+    }
+
+    /// Writes the output as several parts, checking that the input allows it.
+    pub fn set_split(&mut self, mode: SplitMode) -> Result<(), String> {
+        self.split_args = mode.segment_args(&self.source)?;
+        self.split = Some(mode);
+        Ok(())
+    }
+
+    /// Path given to ffmpeg: the output, or the numbered pattern of its parts when splitting.
+    pub fn written_output(&self) -> PathBuf {
+        match self.split {
+            Some(_) => split::part_pattern(&self.output),
+            None => self.output.clone(),
+        }
+    }
+
+    /// First file ffmpeg will create, used to detect existing outputs.
+    pub fn first_output_file(&self) -> PathBuf {
+        match self.split {
+            Some(_) => split::part_path(&split::part_pattern(&self.output), 1),
+            None => self.output.clone(),
+        }
+    }
+
+    /// Lowercase extension of the output, which decides what it can hold.
+    pub fn container(&self) -> String {
+        self.output.extension().unwrap_or_default().to_string_lossy().to_ascii_lowercase()
+    }
+
+    fn subtitle_action(&self, codec: &str) -> (SubtitleAction, Option<String>) {
+        transcode::subtitle_action(codec, &self.container(), self.subtitles)
+    }
+
+    /// Streams of the input as they are laid out in the output; sidecars follow them.
+    pub fn output_streams(&self) -> Vec<OutputStream> {
+        let mut streams = vec![];
+        for stream in self.source.streams.iter().filter(|s| !self.dropped.contains(&s.index)) {
+            if stream.codec_type() == "subtitle" {
+                match self.subtitle_action(stream.codec_name()).0 {
+                    SubtitleAction::Copy => streams.push(OutputStream { source: stream.index, encoding: Encoding::Copy }),
+                    SubtitleAction::Convert(format) => {
+                        streams.push(OutputStream { source: stream.index, encoding: Encoding::Subtitle(format) })
+                    }
+                    SubtitleAction::Drop => {}
+                }
+                continue;
+            }
+            let transcode = self.audio.iter().find(|t| t.stream == stream.index);
+            if transcode.is_none_or(|t| t.keep_original) {
+                streams.push(OutputStream { source: stream.index, encoding: Encoding::Copy });
+            }
+            if let Some(transcode) = transcode {
+                streams.push(OutputStream { source: stream.index, encoding: Encoding::Audio(transcode.clone()) });
+            }
+        }
+        streams
+    }
+
+    /// Sidecars written to the output, with the subtitle format they are converted to.
+    pub fn muxed_sidecars(&self) -> Vec<(&Sidecar, Option<SubtitleFormat>)> {
+        self.sidecars
+            .iter()
+            .filter_map(|sidecar| match sidecar.subtitle_codec().map(|c| self.subtitle_action(c).0) {
+                None | Some(SubtitleAction::Copy) => Some((sidecar, None)),
+                Some(SubtitleAction::Convert(format)) => Some((sidecar, Some(format))),
+                Some(SubtitleAction::Drop) => None,
+            })
+            .collect()
+    }
+
+    /// Edit applying to an output stream; compatibility tracks keep their own title and flags.
+    pub fn edit_of(&self, stream: &OutputStream) -> Option<&TrackEdit> {
+        if matches!(&stream.encoding, Encoding::Audio(t) if t.keep_original) {
+            return None;
+        }
+        self.edits.iter().find(|e| e.stream == stream.source)
+    }
+
+    /// Adds a warning for every subtitle that cannot be written as requested, and for stream
+    /// types losing all their streams.
+    pub fn validate(&mut self) {
+        let mut warnings = vec![];
+        for codec_type in ["video", "audio", "subtitle"] {
+            let mut streams = self.source.streams_of(codec_type).filter(|s| s.disposition.attached_pic == 0).peekable();
+            if streams.peek().is_some() && streams.all(|s| self.dropped.contains(&s.index)) {
+                warnings.push(format!("every {codec_type} stream is dropped"));
+            }
+        }
+        for stream in self.source.streams_of("subtitle").filter(|s| !self.dropped.contains(&s.index)) {
+            if let (_, Some(note)) = self.subtitle_action(stream.codec_name()) {
+                warnings.push(format!("stream {}: {note}", stream.index));
+            }
+        }
+        for sidecar in &self.sidecars {
+            if let Some((_, Some(note))) = sidecar.subtitle_codec().map(|c| self.subtitle_action(c)) {
+                warnings.push(format!("{}: {note}", sidecar.file_name()));
+            }
+        }
+        self.warnings.extend(warnings);
+    }
+
+    /// Expected duration of the output.
+    pub fn duration(&self) -> Option<f64> {
+        if self.segments.is_empty() {
+            self.source.duration()
+        } else {
+            self.segments.iter().map(Segment::length).sum()
+        }
+    }
+
+    /// Files that must exist before ffmpeg runs, with their content.
+    pub fn support_files(&self) -> Vec<(PathBuf, String)> {
+        let mut files = vec![];
+        if !self.segments.is_empty() {
+            files.push((self.temp_path("ffconcat"), self.concat_list()));
+        }
+        if !self.chapters.is_empty() {
+            files.push((self.temp_path("ffmeta"), self.chapter_metadata()));
+        }
+        files
+    }
+
+    fn concat_list(&self) -> String {
+        let mut list = String::from("ffconcat version 1.0\n");
+        for segment in &self.segments {
+            let path = segment.path.to_string_lossy().replace('\'', r"'\''");
+            list.push_str(&format!("file '{path}'\n"));
+            if let Some(inpoint) = segment.inpoint {
+                list.push_str(&format!("inpoint {inpoint:.6}\n"));
+            }
+            if let Some(outpoint) = segment.outpoint {
+                list.push_str(&format!("outpoint {outpoint:.6}\n"));
+            }
+        }
+        list
+    }
+
+    fn chapter_metadata(&self) -> String {
+        let escape = |text: &str| {
+            text.chars().fold(String::new(), |mut escaped, c| {
+                if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+                    escaped.push('\\');
+                }
+                escaped.push(c);
+                escaped
+            })
+        };
+        let mut metadata = String::from(";FFMETADATA1\n");
+        for chapter in &self.chapters {
+            metadata.push_str(&format!(
+                "[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+                (chapter.start * 1000.0).round() as u64,
+                (chapter.end * 1000.0).round() as u64,
+                escape(&chapter.title)
+            ));
+        }
+        metadata
+    }
+
+    fn temp_path(&self, ext: &str) -> PathBuf {
+        let name = template::sanitize(&self.output.file_name().unwrap_or_default().to_string_lossy());
+        match self.job {
+            Some(job) => env::temp_dir().join(format!("fmuxie-{}-job{job}-{name}.{ext}", process::id())),
+            None => env::temp_dir().join(format!("fmuxie-{}-{name}.{ext}", process::id())),
+        }
+    }
+
+    /// Arguments passed to ffmpeg after the global options.
+    pub fn ffmpeg_args(&self) -> Vec<String> {
+        let mut args = vec![];
+        if self.segments.is_empty() {
+            args.extend(["-i".to_string(), self.input.to_string_lossy().into_owned()]);
+        } else {
+            args.extend(["-f", "concat", "-safe", "0", "-i"].map(String::from));
+            args.push(self.temp_path("ffconcat").to_string_lossy().into_owned());
+        }
+        let sidecars = self.muxed_sidecars();
+        for (sidecar, _) in &sidecars {
+            args.push("-i".to_string());
+            args.push(sidecar.path.to_string_lossy().into_owned());
+        }
+        if !self.chapters.is_empty() {
+            args.extend(["-f", "ffmetadata", "-i"].map(String::from));
+            args.push(self.temp_path("ffmeta").to_string_lossy().into_owned());
+            args.extend(["-map_chapters".to_string(), (sidecars.len() + 1).to_string()]);
+        }
+
+        let streams = self.output_streams();
+        for stream in &streams {
+            args.extend(["-map".to_string(), format!("0:{}", stream.source)]);
+        }
+        for input in 1..=sidecars.len() {
+            args.extend(["-map".to_string(), format!("{input}:0")]);
+        }
+        args.extend(["-c".to_string(), "copy".to_string()]);
+
+        for (output, stream) in streams.iter().enumerate() {
+            match &stream.encoding {
+                Encoding::Copy => {}
+                Encoding::Audio(transcode) => {
+                    args.extend(transcode.codec_args(output));
+                    // The compatibility track must not take over from the original.
+                    if transcode.keep_original {
+                        args.extend([format!("-metadata:s:{output}"), format!("title={}", transcode.describe())]);
+                        args.extend([format!("-disposition:{output}"), "0".to_string()]);
+                    }
+                }
+                Encoding::Subtitle(format) => args.extend([format!("-c:{output}"), format.encoder().to_string()]),
+            }
+            if let Some(edit) = self.edit_of(stream)
+                && let Some(source) = self.source.streams.iter().find(|s| s.index == stream.source)
+            {
+                if let Some(language) = &edit.language {
+                    args.extend([format!("-metadata:s:{output}"), format!("language={language}")]);
+                }
+                if let Some(title) = &edit.title {
+                    args.extend([format!("-metadata:s:{output}"), format!("title={title}")]);
+                }
+                if edit.default.is_some() || edit.forced.is_some() {
+                    args.extend([format!("-disposition:{output}"), edit.disposition(source)]);
+                }
+            }
+        }
+
+        // Sidecars come right after every stream of the main input.
+        for (i, (sidecar, format)) in sidecars.iter().enumerate() {
+            let stream = streams.len() + i;
+            if let Some(format) = format {
+                args.extend([format!("-c:{stream}"), format.encoder().to_string()]);
+            }
+            if let Some(language) = &sidecar.language {
+                args.extend([format!("-metadata:s:{stream}"), format!("language={language}")]);
+            }
+            if let Some(title) = &sidecar.title {
+                args.extend([format!("-metadata:s:{stream}"), format!("title={title}")]);
+            }
+            let mut disposition = vec![];
+            if sidecar.default {
+                disposition.push("default");
+            }
+            if sidecar.forced {
+                disposition.push("forced");
+            }
+            if sidecar.hearing_impaired {
+                disposition.push("hearing_impaired");
+            }
+            if sidecar.commentary {
+                disposition.push("comment");
+            }
+            args.extend([
+                format!("-disposition:{stream}"),
+                if disposition.is_empty() { "0".to_string() } else { disposition.join("+") },
+            ]);
+        }
+
+        if self.faststart && mp4::is_mp4_container(&self.container()) {
+            args.extend(["-movflags".to_string(), "+faststart".to_string()]);
+        }
+        args.extend(self.split_args.iter().cloned());
+        args.push(if self.overwrite { "-y" } else { "-n" }.to_string());
+        args.push(self.written_output().to_string_lossy().into_owned());
+        args
+    }
+}
@@ -0,0 +1,338 @@
+use std::{
+    collections::HashMap,
+    env,
+    ffi::CString,
+    fs, io,
+    os::unix::{ffi::OsStrExt, fs::MetadataExt},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    ffmpeg::probe::{ProbeInfo, StreamInfo},
+    units::human_bytes,
+};
+
+use super::{
+    extract::ExtractPlan,
+    plan::{Encoding, RemuxPlan},
+    split,
+    transcode::AudioTranscode,
+};
+
+/// Room kept on top of the estimate for container overhead and estimation errors.
+const MARGIN: f64 = 1.05;
+
+/// Space check of a job that may run.
+#[derive(Debug, Clone)]
+pub struct Preflight {
+    pub estimate: Option<u64>,
+    /// Free space left once running jobs are done, on the fullest filesystem written to.
+    pub free: u64,
+    /// Part of the free space held for running jobs.
+    pub reserved: u64,
+    /// Space the job holds while it runs.
+    pub claims: Vec<Claim>,
+}
+
+impl Preflight {
+    pub fn describe(&self) -> String {
+        let mut text = match self.estimate {
+            Some(estimate) => format!(
+                "about {} needed, {} free",
+                human_bytes(estimate as f64),
+                human_bytes(self.free as f64)
+            ),
+            None => format!("size unknown, {} free", human_bytes(self.free as f64)),
+        };
+        if self.reserved > 0 {
+            text.push_str(&format!(" besides {} held for running jobs", human_bytes(self.reserved as f64)));
+        }
+        text
+    }
+}
+
+/// Space a running job is expected to take on one filesystem.
+#[derive(Debug, Clone)]
+pub struct Claim {
+    /// `st_dev` of the filesystem.
+    pub device: u64,
+    pub bytes: u64,
+    /// Outputs counted against `bytes` as they grow.
+    pub files: Vec<PathBuf>,
+}
+
+/// Bytes running jobs have yet to write, by filesystem.
+pub type Reserved = HashMap<u64, u64>;
+
+/// What the claims still hold once the files already written are taken off, as those are
+/// no longer free.
+pub fn reserved<'a>(claims: impl IntoIterator<Item = &'a Claim>) -> Reserved {
+    let mut reserved = Reserved::new();
+    for claim in claims {
+        let written: u64 = claim
+            .files
+            .iter()
+            .flat_map(|p| split::expand(p))
+            .filter_map(|p| fs::metadata(p).ok())
+            .map(|m| m.len())
+            .sum();
+        *reserved.entry(claim.device).or_default() += claim.bytes.saturating_sub(written);
+    }
+    reserved
+}
+
+/// Files a job writes into one directory.
+struct Destination {
+    dir: PathBuf,
+    estimate: Option<u64>,
+    files: Vec<PathBuf>,
+}
+
+/// Bytes of each stream of a probe, from the Matroska statistics tags, the stream bitrate,
+/// or an even share of what is left of the file size.
+pub fn stream_sizes(probe: &ProbeInfo) -> Vec<Option<u64>> {
+    let duration = probe.duration();
+    let mut sizes: Vec<Option<u64>> = probe.streams.iter().map(|s| known_size(s, duration)).collect();
+    let total: Option<u64> = probe.format.size.as_deref().and_then(|s| s.parse().ok());
+    let unknown = sizes.iter().filter(|s| s.is_none()).count();
+    if let Some(total) = total
+        && unknown > 0
+    {
+        let known: u64 = sizes.iter().flatten().sum();
+        let share = total.saturating_sub(known) / unknown as u64;
+        for size in sizes.iter_mut().filter(|s| s.is_none()) {
+            *size = Some(share);
+        }
+    }
+    sizes
+}
+
+fn known_size(stream: &StreamInfo, duration: Option<f64>) -> Option<u64> {
+    let tag = |name: &str| {
+        stream
+            .tags
+            .iter()
+            .find(|(key, _)| *key == name || key.starts_with(&format!("{name}-")))
+            .and_then(|(_, value)| value.parse::<u64>().ok())
+    };
+    if let Some(bytes) = tag("NUMBER_OF_BYTES") {
+        return Some(bytes);
+    }
+    let bit_rate = stream.bit_rate.as_deref().and_then(|b| b.parse::<u64>().ok()).or_else(|| tag("BPS"))?;
+    let duration = stream.duration.as_deref().and_then(|d| d.parse::<f64>().ok()).or(duration)?;
+    Some((bit_rate as f64 * duration / 8.0) as u64)
+}
+
+/// Expected size of the output of a remux.
+pub fn estimated_size(plan: &RemuxPlan) -> Option<u64> {
+    let source_duration = plan.source.duration()?;
+    let duration = plan.duration()?;
+    // Joined parts are assumed to look like the first one.
+    let ratio = duration / source_duration;
+    let sizes = stream_sizes(&plan.source);
+    let mut total = 0.0;
+    for stream in plan.output_streams() {
+        let position = plan.source.streams.iter().position(|s| s.index == stream.source)?;
+        total += match &stream.encoding {
+            Encoding::Audio(AudioTranscode { bitrate: Some(kbps), .. }) => *kbps as f64 * 1000.0 / 8.0 * duration,
+            _ => sizes[position]? as f64 * ratio,
+        };
+    }
+    for (sidecar, _) in plan.muxed_sidecars() {
+        total += fs::metadata(&sidecar.path).map(|m| m.len()).unwrap_or(0) as f64;
+    }
+    Some(total as u64)
+}
+
+/// Checks that the output directory and the temp directory are writable and that the
+/// output, and the backup of an in-place remux going to `backup_dir`, fit on their
+/// filesystems next to what running jobs still have to write.
+pub fn check(plan: &RemuxPlan, backup_dir: Option<&Path>, reserved: &Reserved) -> Result<Preflight, String> {
+    if !plan.support_files().is_empty() {
+        check_writable(&env::temp_dir())?;
+    }
+    // An overwritten output is truncated first, its space comes back.
+    let reclaimed = match plan.overwrite {
+        true => fs::metadata(plan.first_output_file()).map(|m| m.len()).unwrap_or(0),
+        false => 0,
+    };
+    let output = plan.written_output();
+    let mut destinations = vec![Destination {
+        dir: directory_of(&output),
+        estimate: estimated_size(plan).map(|size| size.saturating_sub(reclaimed)),
+        files: vec![output],
+    }];
+    // The backup is a hard link on the filesystem of the input and a full copy anywhere else.
+    if plan.in_place
+        && let Some(dir) = backup_dir
+        && let Ok(input) = fs::metadata(&plan.input)
+    {
+        let dir = existing_ancestor(dir);
+        if fs::metadata(&dir).is_ok_and(|m| m.dev() != input.dev()) {
+            destinations.push(Destination { dir, estimate: Some(input.len()), files: vec![] });
+        }
+    }
+    check_destinations(destinations, reserved)
+}
+
+pub fn check_extract(plan: &ExtractPlan, probe: &ProbeInfo, reserved: &Reserved) -> Result<Preflight, String> {
+    let sizes = stream_sizes(probe);
+    let destinations = plan
+        .outputs
+        .iter()
+        .map(|output| Destination {
+            dir: directory_of(&output.path),
+            estimate: probe.streams.iter().position(|s| s.index == output.stream).and_then(|i| sizes[i]),
+            files: vec![output.path.clone()],
+        })
+        .collect();
+    check_destinations(destinations, reserved)
+}
+
+/// Checks every directory, then the sum of the estimates of each filesystem against its
+/// free space once.
+fn check_destinations(destinations: Vec<Destination>, reserved: &Reserved) -> Result<Preflight, String> {
+    let mut filesystems: Vec<(u64, Destination)> = vec![];
+    for destination in destinations {
+        let dir = &destination.dir;
+        if !dir.is_dir() {
+            return Err(format!("directory {} does not exist", dir.display()));
+        }
+        check_writable(dir)?;
+        let device = fs::metadata(dir).map_err(|err| format!("cannot read {}: {err}", dir.display()))?.dev();
+        match filesystems.iter_mut().find(|(d, _)| *d == device) {
+            Some((_, total)) => {
+                total.estimate = total.estimate.zip(destination.estimate).map(|(a, b)| a + b);
+                total.files.extend(destination.files);
+            }
+            None => filesystems.push((device, destination)),
+        }
+    }
+
+    let mut preflight = Preflight { estimate: Some(0), free: u64::MAX, reserved: 0, claims: vec![] };
+    for (device, Destination { dir, estimate, files }) in filesystems {
+        let held = reserved.get(&device).copied().unwrap_or(0);
+        let free = free_space(&dir).map_err(|err| format!("cannot read free space of {}: {err}", dir.display()))?;
+        let checked = Preflight { estimate, free: free.saturating_sub(held), reserved: held, claims: vec![] };
+        if let Some(estimate) = estimate
+            && estimate as f64 * MARGIN > checked.free as f64
+        {
+            return Err(format!("not enough space in {}: {}", dir.display(), checked.describe()));
+        }
+        preflight.estimate = preflight.estimate.zip(estimate).map(|(a, b)| a + b);
+        if checked.free < preflight.free {
+            preflight.free = checked.free;
+            preflight.reserved = held;
+        }
+        if let Some(estimate) = estimate {
+            preflight.claims.push(Claim { device, bytes: (estimate as f64 * MARGIN) as u64, files });
+        }
+    }
+    Ok(preflight)
+}
+
+fn directory_of(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// `dir` or its closest parent that exists, for directories created when the job ends.
+fn existing_ancestor(dir: &Path) -> PathBuf {
+    dir.ancestors()
+        .find(|d| !d.as_os_str().is_empty() && d.is_dir())
+        .map_or_else(|| PathBuf::from("."), Path::to_path_buf)
+}
+
+fn c_path(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes()).map_err(io::Error::other)
+}
+
+fn check_writable(dir: &Path) -> Result<(), String> {
+    let writable = c_path(dir).is_ok_and(|path| unsafe { libc::access(path.as_ptr(), libc::W_OK) } == 0);
+    if writable { Ok(()) } else { Err(format!("{} is not writable", dir.display())) }
+}
+
+/// Bytes available to unprivileged users on the filesystem holding `dir`.
+fn free_space(dir: &Path) -> io::Result<u64> {
+    let path = c_path(dir)?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use super::*;
+    use crate::remux::extract::ExtractOutput;
+
+    /// Extraction of two streams into the temp directory, each about `size` bytes.
+    fn extraction(name: &str, size: u64) -> (ExtractPlan, ProbeInfo) {
+        let probe = serde_json::from_str(&format!(
+            r#"{{"streams": [
+                {{"index": 1, "codec_type": "audio", "codec_name": "ac3", "tags": {{"NUMBER_OF_BYTES-eng": "{size}"}}}},
+                {{"index": 2, "codec_type": "audio", "codec_name": "ac3", "tags": {{"NUMBER_OF_BYTES-eng": "{size}"}}}}
+            ]}}"#
+        ))
+        .unwrap();
+        let output = |stream: usize| ExtractOutput {
+            stream,
+            path: env::temp_dir().join(format!("fmuxie-{}-{name}-{stream}.ac3", process::id())),
+            format: "ac3".to_string(),
+            codec: "copy".to_string(),
+            bitstream_filter: None,
+        };
+        let plan = ExtractPlan {
+            input: PathBuf::from("/media/in/Movie.mkv"),
+            duration: None,
+            outputs: vec![output(1), output(2)],
+            overwrite: false,
+        };
+        (plan, probe)
+    }
+
+    #[test]
+    fn adds_up_the_outputs_of_a_filesystem() {
+        let free = free_space(&env::temp_dir()).unwrap();
+        // Either stream fits on its own, both do not.
+        let (mut plan, probe) = extraction("sum", free / 10 * 6);
+        let err = check_extract(&plan, &probe, &Reserved::new()).unwrap_err();
+        assert!(err.starts_with("not enough space in"), "{err}");
+        plan.outputs.pop();
+        let checked = check_extract(&plan, &probe, &Reserved::new()).unwrap();
+        assert_eq!(checked.estimate, Some(free / 10 * 6));
+        assert_eq!(checked.claims.len(), 1);
+    }
+
+    #[test]
+    fn leaves_room_for_running_jobs() {
+        let dir = env::temp_dir();
+        let free = free_space(&dir).unwrap();
+        let device = fs::metadata(&dir).unwrap().dev();
+        let (mut plan, probe) = extraction("reserved", free / 10 * 3);
+        plan.outputs.pop();
+        assert!(check_extract(&plan, &probe, &Reserved::new()).is_ok());
+        let reserved = Reserved::from([(device, free / 10 * 8)]);
+        let err = check_extract(&plan, &probe, &reserved).unwrap_err();
+        assert!(err.contains("held for running jobs"), "{err}");
+    }
+
+    #[test]
+    fn claims_shrink_as_their_files_are_written() {
+        let path = env::temp_dir().join(format!("fmuxie-{}-claim.mkv", process::id()));
+        fs::write(&path, [0; 300]).unwrap();
+        let claims = [
+            Claim { device: 7, bytes: 1000, files: vec![path.clone()] },
+            Claim { device: 7, bytes: 50, files: vec![] },
+            Claim { device: 8, bytes: 100, files: vec![path.clone()] },
+        ];
+        let reserved = reserved(&claims);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(reserved, Reserved::from([(7, 750), (8, 0)]));
+    }
+}
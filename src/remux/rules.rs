@@ -0,0 +1,302 @@
+use crate::ffmpeg::probe::StreamInfo;
+
+use super::{edit::TrackEdit, plan::RemuxPlan};
+
+/// What a rule does to the streams it matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Once a type has a keep rule, its streams no keep rule matches are dropped.
+    Keep,
+    Drop,
+    /// Makes the first match the default stream of its type.
+    Default,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Video,
+    Audio,
+    Subtitle,
+    Any,
+}
+
+impl Kind {
+    /// Cover art, attachments and data streams are never selected by rules.
+    fn of(stream: &StreamInfo) -> Option<Kind> {
+        match stream.codec_type() {
+            "video" if stream.disposition.attached_pic == 0 => Some(Kind::Video),
+            "audio" => Some(Kind::Audio),
+            "subtitle" => Some(Kind::Subtitle),
+            _ => None,
+        }
+    }
+
+    fn covers(self, kind: Kind) -> bool {
+        self == Kind::Any || self == kind
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Test {
+    Language(Vec<String>),
+    Codec(Vec<String>),
+    Title(String),
+    Forced,
+    Default,
+    Commentary,
+    HearingImpaired,
+}
+
+impl Test {
+    fn matches(&self, stream: &StreamInfo) -> bool {
+        let title = stream.title().unwrap_or_default().to_lowercase();
+        match self {
+            Test::Language(languages) => languages.iter().any(|l| l == stream.language().unwrap_or("und")),
+            Test::Codec(codecs) => codecs.iter().any(|c| c == stream.codec_name()),
+            Test::Title(text) => title.contains(text),
+            Test::Forced => stream.disposition.forced == 1,
+            Test::Default => stream.disposition.default == 1,
+            Test::Commentary => stream.disposition.comment == 1 || title.contains("commentary"),
+            Test::HearingImpaired => stream.disposition.hearing_impaired == 1 || title.contains("sdh"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Term {
+    negated: bool,
+    test: Test,
+}
+
+/// One line such as `keep audio where language in [eng, fre] and not commentary`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub action: Action,
+    kind: Kind,
+    /// Alternatives joined by `or`, each a list of terms joined by `and`; empty matches all.
+    condition: Vec<Vec<Term>>,
+}
+
+impl Rule {
+    /// Parses `<keep|drop|default> [first] <video|audio|subs|streams> [where <condition>]`.
+    /// Conditions combine `language in [eng, fre]`, `language = eng`, `codec in [...]`,
+    /// `title has <text>`, `forced`, `default`, `commentary` and `sdh` with `not`, `and`, `or`.
+    pub fn parse(text: &str) -> Result<Rule, String> {
+        let spaced = text.to_lowercase().replace(['[', ']', ','], " ");
+        let mut tokens = spaced.split_whitespace().peekable();
+        let action = match tokens.next() {
+            Some("keep") => Action::Keep,
+            Some("drop") => Action::Drop,
+            Some("default") => Action::Default,
+            Some(other) => return Err(format!("unknown action \"{other}\", expected keep, drop or default")),
+            None => return Err("empty rule".to_string()),
+        };
+        tokens.next_if_eq(&"first");
+        let kind = match tokens.next() {
+            Some("video") => Kind::Video,
+            Some("audio") => Kind::Audio,
+            Some("sub" | "subs" | "subtitle" | "subtitles") => Kind::Subtitle,
+            Some("stream" | "streams" | "all") => Kind::Any,
+            Some(other) => return Err(format!("unknown stream type \"{other}\"")),
+            None => return Err("missing stream type".to_string()),
+        };
+        let mut condition = vec![];
+        match tokens.next() {
+            None => {}
+            Some("where") => {
+                let words: Vec<&str> = tokens.collect();
+                for alternative in words.split(|w| *w == "or") {
+                    let terms = alternative.split(|w| *w == "and").map(parse_term).collect::<Result<Vec<_>, _>>()?;
+                    condition.push(terms);
+                }
+            }
+            Some(other) => return Err(format!("expected where, found \"{other}\"")),
+        }
+        Ok(Rule { action, kind, condition })
+    }
+
+    fn matches(&self, stream: &StreamInfo) -> bool {
+        Kind::of(stream).is_some_and(|kind| self.kind.covers(kind))
+            && (self.condition.is_empty()
+                || self.condition.iter().any(|terms| terms.iter().all(|t| t.test.matches(stream) != t.negated)))
+    }
+}
+
+fn parse_term(words: &[&str]) -> Result<Term, String> {
+    let (negated, words) = match words {
+        ["not", rest @ ..] => (true, rest),
+        _ => (false, words),
+    };
+    let list = |values: &[&str]| -> Result<Vec<String>, String> {
+        match values {
+            ["in" | "=" | "is", values @ ..] if !values.is_empty() => Ok(values.iter().map(|v| v.to_string()).collect()),
+            _ => Err(format!("expected a value after \"{}\"", words.join(" "))),
+        }
+    };
+    let test = match words {
+        ["language" | "lang", values @ ..] => Test::Language(list(values)?),
+        ["codec", values @ ..] => Test::Codec(list(values)?),
+        ["title", "has" | "contains", text @ ..] if !text.is_empty() => Test::Title(text.join(" ")),
+        ["forced"] => Test::Forced,
+        ["default"] => Test::Default,
+        ["commentary"] => Test::Commentary,
+        ["sdh" | "hearing_impaired"] => Test::HearingImpaired,
+        [] => return Err("empty condition".to_string()),
+        _ => return Err(format!("unknown condition \"{}\"", words.join(" "))),
+    };
+    Ok(Term { negated, test })
+}
+
+/// Parses one rule per entry, blank entries being ignored.
+pub fn parse_all<S: AsRef<str>>(texts: &[S]) -> Result<Vec<Rule>, String> {
+    texts
+        .iter()
+        .map(|t| t.as_ref().trim())
+        .filter(|t| !t.is_empty())
+        .map(|t| Rule::parse(t).map_err(|err| format!("{err} in \"{t}\"")))
+        .collect()
+}
+
+/// Indexes of the streams the rules leave out.
+pub fn dropped(rules: &[Rule], streams: &[StreamInfo]) -> Vec<usize> {
+    let mut dropped = vec![];
+    for stream in streams {
+        let Some(kind) = Kind::of(stream) else {
+            continue;
+        };
+        let mut keeps = rules.iter().filter(|r| r.action == Action::Keep && r.kind.covers(kind)).peekable();
+        let kept = keeps.peek().is_none() || keeps.any(|r| r.matches(stream));
+        if !kept || rules.iter().any(|r| r.action == Action::Drop && r.matches(stream)) {
+            dropped.push(stream.index);
+        }
+    }
+    dropped
+}
+
+/// Drops and flags the streams of the plan according to the rules, against its own probe so
+/// that files with different track orders get the same selection.
+pub fn apply(rules: &[Rule], plan: &mut RemuxPlan) {
+    if rules.is_empty() {
+        return;
+    }
+    let streams = &plan.source.streams;
+    let dropped = dropped(rules, streams);
+
+    let mut edits: Vec<TrackEdit> = vec![];
+    for rule in rules.iter().filter(|r| r.action == Action::Default) {
+        let kept = || streams.iter().filter(|s| !dropped.contains(&s.index));
+        let Some(first) = kept().find(|s| rule.matches(s)) else {
+            continue;
+        };
+        for stream in kept().filter(|s| Kind::of(s) == Kind::of(first)) {
+            let default = stream.index == first.index;
+            if default == (stream.disposition.default == 1) {
+                continue;
+            }
+            match edits.iter_mut().find(|e| e.stream == stream.index) {
+                Some(edit) => edit.default = Some(default),
+                None => edits.push(TrackEdit { stream: stream.index, default: Some(default), ..TrackEdit::default() }),
+            }
+        }
+    }
+    plan.dropped = dropped;
+    for edit in edits {
+        match plan.edits.iter_mut().find(|e| e.stream == edit.stream) {
+            Some(existing) => existing.default = edit.default,
+            None => plan.edits.push(edit),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::ffmpeg::probe::ProbeInfo;
+
+    const PROBE: &str = r#"{"streams": [
+        {"index": 0, "codec_type": "video", "codec_name": "h264", "disposition": {"default": 1}},
+        {"index": 1, "codec_type": "audio", "codec_name": "dts", "tags": {"language": "eng"}, "disposition": {"default": 1}},
+        {"index": 2, "codec_type": "audio", "codec_name": "ac3", "tags": {"language": "fre"}},
+        {"index": 3, "codec_type": "audio", "codec_name": "ac3", "tags": {"language": "eng", "title": "Director's Commentary"}},
+        {"index": 4, "codec_type": "subtitle", "codec_name": "subrip", "tags": {"language": "eng"}},
+        {"index": 5, "codec_type": "subtitle", "codec_name": "subrip", "tags": {"language": "fre"}, "disposition": {"forced": 1}},
+        {"index": 6, "codec_type": "subtitle", "codec_name": "hdmv_pgs_subtitle", "tags": {"language": "ger"}},
+        {"index": 7, "codec_type": "video", "codec_name": "mjpeg", "disposition": {"attached_pic": 1}}
+    ]}"#;
+
+    #[test]
+    fn parses_rules() {
+        let rule = Rule::parse("keep audio where language in [eng, fre] and not commentary").unwrap();
+        assert_eq!(rule.action, Action::Keep);
+        assert_eq!(rule.kind, Kind::Audio);
+        assert_eq!(rule.condition.len(), 1);
+        assert_eq!(rule.condition[0].len(), 2);
+        assert!(rule.condition[0][1].negated);
+
+        let rule = Rule::parse("Default first SUBS where forced or title has Signs & Songs").unwrap();
+        assert_eq!(rule.action, Action::Default);
+        assert_eq!(rule.kind, Kind::Subtitle);
+        let forced = Term { negated: false, test: Test::Forced };
+        let title = Term { negated: false, test: Test::Title("signs & songs".to_string()) };
+        assert_eq!(rule.condition, [[forced], [title]]);
+        assert_eq!(parse_all(&["", "  ", "drop streams"]).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn reports_parse_errors() {
+        assert!(Rule::parse("").unwrap_err().contains("empty"));
+        assert!(Rule::parse("remove audio").unwrap_err().contains("unknown action"));
+        assert!(Rule::parse("keep music").unwrap_err().contains("unknown stream type"));
+        assert!(Rule::parse("keep audio if forced").unwrap_err().contains("expected where"));
+        assert!(Rule::parse("keep audio where language in").is_err());
+        assert!(Rule::parse("keep audio where loud").unwrap_err().contains("unknown condition"));
+        assert!(Rule::parse("keep audio where forced and").is_err());
+        assert!(parse_all(&["keep audio", "drop"]).unwrap_err().contains("in \"drop\""));
+    }
+
+    #[test]
+    fn keep_rules_drop_the_other_streams_of_their_type() {
+        let probe: ProbeInfo = serde_json::from_str(PROBE).unwrap();
+        let rules =
+            parse_all(&["keep audio where language = eng and not commentary", "keep subs where language in [eng, fre]"])
+                .unwrap();
+        // Video has no keep rule and cover art is never touched.
+        assert_eq!(dropped(&rules, &probe.streams), [2, 3, 6]);
+    }
+
+    #[test]
+    fn drop_rules_apply_on_top_of_keep_rules() {
+        let probe: ProbeInfo = serde_json::from_str(PROBE).unwrap();
+        let rules = parse_all(&[
+            "keep subs where language = eng or forced",
+            "drop streams where codec = hdmv_pgs_subtitle",
+            "drop audio where commentary",
+        ])
+        .unwrap();
+        assert_eq!(dropped(&rules, &probe.streams), [3, 6]);
+        assert!(dropped(&[], &probe.streams).is_empty());
+    }
+
+    #[test]
+    fn default_rules_move_the_flag_among_kept_streams() {
+        let probe: ProbeInfo = serde_json::from_str(PROBE).unwrap();
+        let mut plan = RemuxPlan::new(PathBuf::from("in.mkv"), PathBuf::from("out.mkv"), probe);
+        let rules =
+            parse_all(&["drop audio where language = eng", "default first audio", "default subs where forced"]).unwrap();
+        apply(&rules, &mut plan);
+        assert_eq!(plan.dropped, [1, 3]);
+        let flags: Vec<(usize, Option<bool>)> = plan.edits.iter().map(|e| (e.stream, e.default)).collect();
+        // Stream 1 was the default but is dropped; no subtitle had the flag before.
+        assert_eq!(flags, [(2, Some(true)), (5, Some(true))]);
+    }
+
+    #[test]
+    fn no_rules_leave_the_plan_alone() {
+        let probe: ProbeInfo = serde_json::from_str(PROBE).unwrap();
+        let mut plan = RemuxPlan::new(PathBuf::from("in.mkv"), PathBuf::from("out.mkv"), probe);
+        apply(&[], &mut plan);
+        assert!(plan.dropped.is_empty() && plan.edits.is_empty());
+    }
+}
@@ -0,0 +1,228 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+const SUBTITLE_EXTENSIONS: &[&str] = &["srt", "ass", "ssa", "vtt", "sup", "sub"];
+const AUDIO_EXTENSIONS: &[&str] = &["ac3", "eac3", "dts", "thd", "aac", "m4a", "mka", "flac", "mp3", "opus", "wav"];
+
+/// ISO 639-1 codes mapped to the ISO 639-2 codes Matroska expects.
+const LANGUAGE_CODES: &[(&str, &str)] = &[
+    ("en", "eng"), ("fr", "fre"), ("de", "ger"), ("es", "spa"), ("it", "ita"),
+    ("pt", "por"), ("nl", "dut"), ("sv", "swe"), ("no", "nor"), ("da", "dan"),
+    ("fi", "fin"), ("pl", "pol"), ("cs", "cze"), ("hu", "hun"), ("ru", "rus"),
+    ("uk", "ukr"), ("el", "gre"), ("tr", "tur"), ("ar", "ara"), ("he", "heb"),
+    ("ja", "jpn"), ("ko", "kor"), ("zh", "chi"), ("th", "tha"), ("hi", "hin"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SidecarKind {
+    Subtitle,
+    Audio,
+}
+
+/// A file next to the video sharing its stem, e.g. `movie.fr.forced.ass`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sidecar {
+    pub path: PathBuf,
+    pub kind: SidecarKind,
+    pub language: Option<String>,
+    pub title: Option<String>,
+    pub forced: bool,
+    pub default: bool,
+    pub hearing_impaired: bool,
+    pub commentary: bool,
+}
+
+impl Sidecar {
+    pub fn file_name(&self) -> String {
+        self.path.file_name().unwrap_or_default().to_string_lossy().into_owned()
+    }
+
+    /// Subtitle codec guessed from the extension, as ffprobe would name it.
+    pub fn subtitle_codec(&self) -> Option<&'static str> {
+        let ext = self.path.extension()?.to_string_lossy().to_ascii_lowercase();
+        match ext.as_str() {
+            "srt" => Some("subrip"),
+            "ass" => Some("ass"),
+            "ssa" => Some("ssa"),
+            "vtt" => Some("webvtt"),
+            "sup" => Some("hdmv_pgs_subtitle"),
+            "sub" => Some("dvd_subtitle"),
+            _ => None,
+        }
+    }
+
+    /// Short description of what was inferred from the file name.
+    pub fn describe(&self) -> String {
+        let mut flags = vec![self.language.clone().unwrap_or_else(|| "und".to_string())];
+        if self.forced {
+            flags.push("forced".to_string());
+        }
+        if self.default {
+            flags.push("default".to_string());
+        }
+        if self.hearing_impaired {
+            flags.push("sdh".to_string());
+        }
+        if self.commentary {
+            flags.push("commentary".to_string());
+        }
+        if let Some(title) = &self.title {
+            flags.push(format!("\"{title}\""));
+        }
+        let kind = match self.kind {
+            SidecarKind::Subtitle => "subtitle",
+            SidecarKind::Audio => "audio",
+        };
+        format!("{} [{kind}] ({})", self.file_name(), flags.join(", "))
+    }
+}
+
+pub fn detect(video: &Path) -> Vec<Sidecar> {
+    let Some(dir) = video.parent() else {
+        return vec![];
+    };
+    let stem = video.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+
+    let mut sidecars: Vec<Sidecar> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| path.is_file() && path.as_path() != video)
+        .filter_map(|path| parse(&path, &stem))
+        .collect();
+    sidecars.sort_by(|a, b| a.path.cmp(&b.path));
+    sidecars
+}
+
+fn parse(path: &Path, stem: &str) -> Option<Sidecar> {
+    let name = path.file_name()?.to_string_lossy().into_owned();
+    let rest = name.strip_prefix(stem)?.strip_prefix('.')?;
+    let (suffixes, ext) = match rest.rsplit_once('.') {
+        Some((suffixes, ext)) => (suffixes, ext),
+        None => ("", rest),
+    };
+    let ext = ext.to_ascii_lowercase();
+    let kind = if SUBTITLE_EXTENSIONS.contains(&ext.as_str()) {
+        SidecarKind::Subtitle
+    } else if AUDIO_EXTENSIONS.contains(&ext.as_str()) {
+        SidecarKind::Audio
+    } else {
+        return None;
+    };
+
+    let mut sidecar = Sidecar {
+        path: path.to_path_buf(),
+        kind,
+        language: None,
+        title: None,
+        forced: false,
+        default: false,
+        hearing_impaired: false,
+        commentary: false,
+    };
+    let mut title_words = vec![];
+    for token in suffixes.split('.').filter(|t| !t.is_empty()) {
+        let lower = token.to_ascii_lowercase();
+        match lower.as_str() {
+            "forced" => sidecar.forced = true,
+            "default" => sidecar.default = true,
+            "sdh" | "cc" => sidecar.hearing_impaired = true,
+            // `hi` is Hindi too, only read as hearing-impaired once a language came before it.
+            "hi" if sidecar.language.is_some() => sidecar.hearing_impaired = true,
+            "commentary" => {
+                sidecar.commentary = true;
+                title_words.push("Commentary".to_string());
+            }
+            _ if sidecar.language.is_none() && language_code(&lower).is_some() => {
+                sidecar.language = language_code(&lower);
+            }
+            _ => title_words.push(token.to_string()),
+        }
+    }
+    if !title_words.is_empty() {
+        sidecar.title = Some(title_words.join(" "));
+    }
+    Some(sidecar)
+}
+
+fn language_code(token: &str) -> Option<String> {
+    if let Some((_, code)) = LANGUAGE_CODES.iter().find(|(short, _)| *short == token) {
+        return Some(code.to_string());
+    }
+    let known = LANGUAGE_CODES.iter().any(|(_, code)| *code == token)
+        || matches!(token, "fra" | "deu" | "nld" | "ces" | "ell" | "zho" | "und");
+    known.then(|| token.to_string())
+}
+
+/// BCP 47 tag of an ISO 639-2 code, which is the ISO 639-1 code when there is one.
+pub fn bcp47(code: &str) -> String {
+    let code = match code {
+        "fra" => "fre",
+        "deu" => "ger",
+        "nld" => "dut",
+        "ces" => "cze",
+        "ell" => "gre",
+        "zho" => "chi",
+        other => other,
+    };
+    LANGUAGE_CODES.iter().find(|(_, long)| *long == code).map_or(code, |(short, _)| short).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(name: &str) -> Sidecar {
+        parse(&Path::new("/media/in").join(name), "movie").unwrap()
+    }
+
+    #[test]
+    fn infers_language_and_flags_from_the_name() {
+        let en = parsed("movie.en.srt");
+        assert_eq!((en.kind, en.language.as_deref(), en.title.as_deref()), (SidecarKind::Subtitle, Some("eng"), None));
+        assert!(!en.forced && !en.hearing_impaired);
+
+        let forced = parsed("movie.fr.forced.ass");
+        assert_eq!(forced.language.as_deref(), Some("fre"));
+        assert!(forced.forced);
+
+        let commentary = parsed("movie.commentary.ac3");
+        assert_eq!(commentary.kind, SidecarKind::Audio);
+        assert_eq!((commentary.language, commentary.title.as_deref()), (None, Some("Commentary")));
+        assert!(commentary.commentary);
+
+        let sdh = parsed("movie.en.sdh.srt");
+        assert_eq!(sdh.language.as_deref(), Some("eng"));
+        assert!(sdh.hearing_impaired);
+        assert_eq!(sdh.describe(), "movie.en.sdh.srt [subtitle] (eng, sdh)");
+    }
+
+    #[test]
+    fn reads_hi_as_hindi_unless_a_language_came_first() {
+        let hindi = parsed("movie.hi.srt");
+        assert_eq!(hindi.language.as_deref(), Some("hin"));
+        assert!(!hindi.hearing_impaired);
+        let english = parsed("movie.en.hi.srt");
+        assert_eq!(english.language.as_deref(), Some("eng"));
+        assert!(english.hearing_impaired);
+    }
+
+    #[test]
+    fn keeps_unknown_words_as_the_title() {
+        let sidecar = parsed("movie.eng.Director's Cut.srt");
+        assert_eq!(sidecar.language.as_deref(), Some("eng"));
+        assert_eq!(sidecar.title.as_deref(), Some("Director's Cut"));
+        assert_eq!(parsed("movie.srt").language, None);
+        assert!(parse(Path::new("/media/in/movie.nfo"), "movie").is_none());
+        assert!(parse(Path::new("/media/in/movies.en.srt"), "movie").is_none());
+        assert_eq!(bcp47("fra"), "fr");
+        assert_eq!(bcp47("hin"), "hi");
+        assert_eq!(bcp47("yue"), "yue");
+    }
+}
@@ -0,0 +1,184 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ffmpeg::probe::ProbeInfo, units::human_bytes};
+
+const PART_PLACEHOLDER: &str = "%03d";
+
+/// How the output of a remux is cut into several files.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SplitMode {
+    Chapters,
+    /// Seconds per part.
+    Duration(f64),
+    /// Bytes per part, approximate: parts are cut by time from the average bitrate.
+    Size(u64),
+}
+
+impl SplitMode {
+    /// Parses `chapters`, a duration such as `30m` / `90s` / `1h`, or a size such as `4G` / `700MB`.
+    pub fn parse(text: &str) -> Result<Option<SplitMode>, String> {
+        let text = text.trim().to_ascii_lowercase();
+        if text.is_empty() || text == "none" {
+            return Ok(None);
+        }
+        if text == "chapters" || text == "chapter" {
+            return Ok(Some(SplitMode::Chapters));
+        }
+        let split = text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+        let (number, unit) = text.split_at(split);
+        let number: f64 = number
+            .parse()
+            .ok()
+            .filter(|n| *n > 0.0)
+            .ok_or_else(|| format!("invalid split \"{text}\""))?;
+        let mode = match unit.trim() {
+            "s" | "sec" => SplitMode::Duration(number),
+            "m" | "min" => SplitMode::Duration(number * 60.0),
+            "h" => SplitMode::Duration(number * 3600.0),
+            "k" | "kb" | "kib" => SplitMode::Size((number * 1024.0) as u64),
+            "mb" | "mib" => SplitMode::Size((number * 1024.0 * 1024.0) as u64),
+            "g" | "gb" | "gib" => SplitMode::Size((number * 1024.0 * 1024.0 * 1024.0) as u64),
+            _ => return Err(format!("unknown unit in \"{text}\", use s/m/h or KB/MB/GB")),
+        };
+        Ok(Some(mode))
+    }
+
+    /// Text accepted by `parse` for this mode.
+    pub fn to_input(self) -> String {
+        match self {
+            SplitMode::Chapters => "chapters".to_string(),
+            SplitMode::Duration(secs) => format!("{}m", secs / 60.0),
+            SplitMode::Size(bytes) => format!("{}MB", bytes / (1024 * 1024)),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            SplitMode::Chapters => "one file per chapter".to_string(),
+            SplitMode::Duration(secs) => format!("one file every {:.0} min", secs / 60.0),
+            SplitMode::Size(bytes) => {
+                format!("files of about {}, cut from the average bitrate", human_bytes(*bytes as f64))
+            }
+        }
+    }
+
+    /// Options for ffmpeg's segment muxer. Parts always start on a keyframe since the
+    /// muxer only cuts there when stream copying.
+    pub fn segment_args(&self, source: &ProbeInfo) -> Result<Vec<String>, String> {
+        let mut args: Vec<String> = ["-f", "segment", "-segment_start_number", "1", "-reset_timestamps", "1"]
+            .map(String::from)
+            .to_vec();
+        match self {
+            SplitMode::Chapters => {
+                let times: Vec<String> = source
+                    .chapters
+                    .iter()
+                    .filter_map(|c| c.start_time.as_deref()?.parse::<f64>().ok())
+                    .filter(|t| *t > 0.0)
+                    .map(|t| format!("{t:.3}"))
+                    .collect();
+                if times.is_empty() {
+                    return Err("the input has no chapters to split on".to_string());
+                }
+                args.extend(["-segment_times".to_string(), times.join(",")]);
+            }
+            SplitMode::Duration(secs) => args.extend(["-segment_time".to_string(), format!("{secs:.3}")]),
+            SplitMode::Size(bytes) => {
+                let secs = seconds_for_size(source, *bytes)
+                    .ok_or_else(|| "cannot estimate the bitrate of the input".to_string())?;
+                args.extend(["-segment_time".to_string(), format!("{secs:.3}")]);
+            }
+        }
+        Ok(args)
+    }
+}
+
+/// Part length keeping files under `bytes` at the average bitrate of the input, with a
+/// margin since parts can only be cut on keyframes.
+fn seconds_for_size(source: &ProbeInfo, bytes: u64) -> Option<f64> {
+    let bit_rate = source
+        .format
+        .bit_rate
+        .as_deref()
+        .and_then(|b| b.parse::<f64>().ok())
+        .or_else(|| {
+            let size: f64 = source.format.size.as_deref()?.parse().ok()?;
+            Some(size * 8.0 / source.duration()?)
+        })
+        .filter(|b| *b > 0.0)?;
+    Some(bytes as f64 * 8.0 / bit_rate * 0.9)
+}
+
+/// Output pattern for the parts of `output`: `name - %03d.ext`.
+pub fn part_pattern(output: &Path) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy().replace('%', "%%");
+    let ext = output.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    output.with_file_name(format!("{stem} - {PART_PLACEHOLDER}{ext}"))
+}
+
+pub fn part_path(pattern: &Path, number: usize) -> PathBuf {
+    let name = pattern.file_name().unwrap_or_default().to_string_lossy();
+    pattern.with_file_name(name.replace(PART_PLACEHOLDER, &format!("{number:03}")).replace("%%", "%"))
+}
+
+/// The parts written so far for a pattern, or the path itself when it is not one.
+pub fn expand(path: &Path) -> Vec<PathBuf> {
+    if !path.to_string_lossy().contains(PART_PLACEHOLDER) {
+        return vec![path.to_path_buf()];
+    }
+    (1..).map(|n| part_path(path, n)).take_while(|p| p.exists()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_modes() {
+        assert_eq!(SplitMode::parse("Chapters").unwrap(), Some(SplitMode::Chapters));
+        assert_eq!(SplitMode::parse("30m").unwrap(), Some(SplitMode::Duration(1800.0)));
+        assert_eq!(SplitMode::parse("1.5h").unwrap(), Some(SplitMode::Duration(5400.0)));
+        assert_eq!(SplitMode::parse("700MB").unwrap(), Some(SplitMode::Size(700 * 1024 * 1024)));
+        assert_eq!(SplitMode::parse("4G").unwrap(), Some(SplitMode::Size(4 * 1024 * 1024 * 1024)));
+        assert_eq!(SplitMode::parse(" none ").unwrap(), None);
+        assert!(SplitMode::parse("10 parsecs").is_err());
+        assert!(SplitMode::parse("0m").is_err());
+        let size = SplitMode::Size(700 * 1024 * 1024);
+        assert_eq!(SplitMode::parse(&size.to_input()).unwrap(), Some(size));
+    }
+
+    #[test]
+    fn size_parts_leave_a_margin_under_the_average_bitrate() {
+        let source: ProbeInfo =
+            serde_json::from_str(r#"{"format": {"duration": "3600.0", "bit_rate": "8000000"}}"#).unwrap();
+        let args = SplitMode::Size(100_000_000).segment_args(&source).unwrap();
+        // 100 MB at 8 Mb/s is 100 s, 90 s with the margin.
+        assert_eq!(args[args.len() - 2..], ["-segment_time", "90.000"]);
+
+        let sized: ProbeInfo =
+            serde_json::from_str(r#"{"format": {"duration": "100.0", "size": "100000000"}}"#).unwrap();
+        assert_eq!(seconds_for_size(&sized, 50_000_000).map(|s| s.round()), Some(45.0));
+        assert!(SplitMode::Size(1).segment_args(&ProbeInfo::default()).is_err());
+    }
+
+    #[test]
+    fn chapter_splits_skip_the_first_chapter_start() {
+        let source: ProbeInfo = serde_json::from_str(
+            r#"{"chapters": [{"id": 0, "start_time": "0.000000"}, {"id": 1, "start_time": "612.5"}]}"#,
+        )
+        .unwrap();
+        let args = SplitMode::Chapters.segment_args(&source).unwrap();
+        assert_eq!(args[args.len() - 2..], ["-segment_times", "612.500"]);
+        assert!(SplitMode::Chapters.segment_args(&ProbeInfo::default()).is_err());
+    }
+
+    #[test]
+    fn names_parts_after_the_output() {
+        let pattern = part_pattern(Path::new("/videos/100% Movie.mkv"));
+        assert_eq!(pattern, Path::new("/videos/100%% Movie - %03d.mkv"));
+        assert_eq!(part_path(&pattern, 7), Path::new("/videos/100% Movie - 007.mkv"));
+        assert_eq!(expand(Path::new("/videos/plain.mkv")), [PathBuf::from("/videos/plain.mkv")]);
+    }
+}
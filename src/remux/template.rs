@@ -0,0 +1,186 @@
+use std::path::{Path, PathBuf};
+
+use crate::ffmpeg::probe::{ProbeInfo, StreamInfo};
+
+/// Placeholders understood by [`render`].
+pub const PLACEHOLDERS: &[&str] = &[
+    "dir", "stem", "ext", "container", "title",
+    "video.codec", "video.width", "video.height",
+    "audio.codec", "audio.channels", "audio.langs",
+    "sub.langs",
+];
+
+/// Builds an output path from a template such as `{dir}/{stem}.{container}`.
+/// Every value except `{dir}` is sanitized so it cannot introduce path separators
+/// or characters rejected by common filesystems. Relative results are placed next to the input.
+pub fn render(template: &str, input: &Path, probe: &ProbeInfo, container: &str) -> Result<PathBuf, String> {
+    let rendered = expand(template, |name| resolve(name, input, probe, container))?;
+    finish(rendered, template, input)
+}
+
+/// Same as [`render`] for one stream of the input, adding `{index}`, `{lang}`, `{codec}`
+/// and `{type}`; `{ext}` and `{container}` are the extension of the extracted file.
+pub fn render_stream(
+    template: &str,
+    input: &Path,
+    probe: &ProbeInfo,
+    stream: &StreamInfo,
+    ext: &str,
+) -> Result<PathBuf, String> {
+    let rendered = expand(template, |name| match name {
+        "index" => Some(stream.index.to_string()),
+        "lang" => Some(stream.language().unwrap_or("und").to_string()),
+        "codec" => Some(stream.codec_name().to_string()),
+        "type" => Some(stream.codec_type().to_string()),
+        "ext" => Some(ext.to_string()),
+        _ => resolve(name, input, probe, ext),
+    })?;
+    finish(rendered, template, input)
+}
+
+fn expand(template: &str, resolve: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("unclosed placeholder in \"{template}\""))?;
+        let name = &rest[start + 1..start + end];
+        let value = resolve(name).ok_or_else(|| format!("unknown placeholder {{{name}}}"))?;
+        if name == "dir" {
+            rendered.push_str(&value);
+        } else {
+            rendered.push_str(&sanitize(&value));
+        }
+        rest = &rest[start + end + 1..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+fn finish(rendered: String, template: &str, input: &Path) -> Result<PathBuf, String> {
+    // `Path::file_name` would take the last directory of `dir/` as the file name.
+    let path = PathBuf::from(&rendered);
+    let file_name = path
+        .file_name()
+        .filter(|_| !rendered.ends_with('/'))
+        .map(|name| name.to_string_lossy().trim_end_matches(['.', ' ']).trim().to_string())
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| format!("\"{template}\" does not produce a file name"))?;
+    let path = path.with_file_name(file_name);
+
+    if path.is_relative() {
+        Ok(input.parent().unwrap_or(Path::new(".")).join(path))
+    } else {
+        Ok(path)
+    }
+}
+
+fn resolve(name: &str, input: &Path, probe: &ProbeInfo, container: &str) -> Option<String> {
+    let video = probe.streams_of("video").next();
+    let audio = probe.streams_of("audio").next();
+    let value = match name {
+        "dir" => input.parent().unwrap_or(Path::new(".")).to_string_lossy().into_owned(),
+        "stem" => input.file_stem().unwrap_or_default().to_string_lossy().into_owned(),
+        "ext" => input.extension().unwrap_or_default().to_string_lossy().into_owned(),
+        "container" => container.to_string(),
+        "title" => probe.format.tags.get("title").cloned().unwrap_or_default(),
+        "video.codec" => video.map(|s| s.codec_name().to_string()).unwrap_or_default(),
+        "video.width" => video.and_then(|s| s.width).map(|w| w.to_string()).unwrap_or_default(),
+        "video.height" => video.and_then(|s| s.height).map(|h| h.to_string()).unwrap_or_default(),
+        "audio.codec" => audio.map(|s| s.codec_name().to_string()).unwrap_or_default(),
+        "audio.channels" => audio.and_then(|s| s.channels).map(|c| c.to_string()).unwrap_or_default(),
+        "audio.langs" => languages(probe, "audio"),
+        "sub.langs" => languages(probe, "subtitle"),
+        _ => return None,
+    };
+    Some(value)
+}
+
+fn languages(probe: &ProbeInfo, codec_type: &str) -> String {
+    let mut langs: Vec<&str> = Vec::new();
+    for lang in probe.streams_of(codec_type).filter_map(|s| s.language()) {
+        if !langs.contains(&lang) {
+            langs.push(lang);
+        }
+    }
+    langs.join("+")
+}
+
+pub fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROBE: &str = r#"{
+        "streams": [
+            {"index": 0, "codec_type": "video", "codec_name": "hevc", "width": 3840, "height": 2160},
+            {"index": 1, "codec_type": "audio", "codec_name": "truehd", "channels": 8, "tags": {"language": "eng"}},
+            {"index": 2, "codec_type": "audio", "codec_name": "ac3", "channels": 6, "tags": {"language": "fre"}},
+            {"index": 3, "codec_type": "audio", "codec_name": "ac3", "channels": 2, "tags": {"language": "eng"}},
+            {"index": 4, "codec_type": "subtitle", "codec_name": "subrip", "tags": {"language": "spa"}}
+        ],
+        "format": {"filename": "/media/in/Movie.mkv", "tags": {"title": "Alien: Director's Cut"}}
+    }"#;
+
+    #[test]
+    fn renders_probe_values() {
+        let probe: ProbeInfo = serde_json::from_str(PROBE).unwrap();
+        let template = "{dir}/{title} {video.height}p {video.codec} {audio.codec} {audio.channels}ch {audio.langs}.{container}";
+        let path = render(template, Path::new("/media/in/Movie.mkv"), &probe, "mkv").unwrap();
+        assert_eq!(path, Path::new("/media/in/Alien_ Director's Cut 2160p hevc truehd 8ch eng+fre.mkv"));
+        let path = render("{stem}.{ext}.{sub.langs}", Path::new("/media/in/Movie.mkv"), &probe, "mp4").unwrap();
+        assert_eq!(path, Path::new("/media/in/Movie.mkv.spa"));
+    }
+
+    #[test]
+    fn relative_results_go_next_to_the_input() {
+        let probe: ProbeInfo = serde_json::from_str(PROBE).unwrap();
+        let path = render("remuxed/{stem}.{container}", Path::new("/media/in/Movie.mkv"), &probe, "mp4").unwrap();
+        assert_eq!(path, Path::new("/media/in/remuxed/Movie.mp4"));
+    }
+
+    #[test]
+    fn empty_values_do_not_leave_trailing_dots() {
+        let probe = ProbeInfo::default();
+        let path = render("{dir}/{stem}.{title}", Path::new("/media/in/clip.ts"), &probe, "mkv").unwrap();
+        assert_eq!(path, Path::new("/media/in/clip"));
+        assert!(render("{dir}/{title}", Path::new("/media/in/clip.ts"), &probe, "mkv").is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_and_unclosed_placeholders() {
+        let probe = ProbeInfo::default();
+        let input = Path::new("/media/in/clip.ts");
+        assert_eq!(render("{dir}/{bogus}", input, &probe, "mkv").unwrap_err(), "unknown placeholder {bogus}");
+        assert!(render("{dir}/{stem", input, &probe, "mkv").is_err());
+    }
+
+    #[test]
+    fn renders_stream_placeholders() {
+        let probe: ProbeInfo = serde_json::from_str(PROBE).unwrap();
+        let input = Path::new("/media/in/Movie.mkv");
+        let template = "{dir}/{stem}.track{index}.{lang}.{codec}.{type}.{ext}";
+        let path = render_stream(template, input, &probe, &probe.streams[2], "ac3").unwrap();
+        assert_eq!(path, Path::new("/media/in/Movie.track2.fre.ac3.audio.ac3"));
+        let path = render_stream(template, input, &probe, &probe.streams[0], "hevc").unwrap();
+        assert_eq!(path, Path::new("/media/in/Movie.track0.und.hevc.video.hevc"));
+    }
+
+    #[test]
+    fn sanitizes_separators_and_reserved_characters() {
+        assert_eq!(sanitize("AC/DC: Live? <1991> \"Donington\"|x\\y*"), "AC_DC_ Live_ _1991_ _Donington__x_y_");
+        assert_eq!(sanitize("tab\there"), "tab_here");
+    }
+}
@@ -0,0 +1,271 @@
+use serde::{Deserialize, Serialize};
+
+use crate::ffmpeg::probe::StreamInfo;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AudioCodec {
+    Ac3,
+    Eac3,
+    Aac,
+    Flac,
+}
+
+impl AudioCodec {
+    fn parse(text: &str) -> Option<AudioCodec> {
+        match text {
+            "ac3" => Some(AudioCodec::Ac3),
+            "eac3" | "e-ac3" => Some(AudioCodec::Eac3),
+            "aac" => Some(AudioCodec::Aac),
+            "flac" => Some(AudioCodec::Flac),
+            _ => None,
+        }
+    }
+
+    pub fn encoder(self) -> &'static str {
+        match self {
+            AudioCodec::Ac3 => "ac3",
+            AudioCodec::Eac3 => "eac3",
+            AudioCodec::Aac => "aac",
+            AudioCodec::Flac => "flac",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AudioCodec::Ac3 => "AC3",
+            AudioCodec::Eac3 => "E-AC3",
+            AudioCodec::Aac => "AAC",
+            AudioCodec::Flac => "FLAC",
+        }
+    }
+
+    /// Kbit/s used when none is given; FLAC is lossless and takes none.
+    fn default_bitrate(self) -> Option<u32> {
+        match self {
+            AudioCodec::Ac3 | AudioCodec::Eac3 => Some(640),
+            AudioCodec::Aac => Some(256),
+            AudioCodec::Flac => None,
+        }
+    }
+
+    /// The Dolby encoders of ffmpeg stop at 5.1.
+    fn max_channels(self) -> Option<u32> {
+        match self {
+            AudioCodec::Ac3 | AudioCodec::Eac3 => Some(6),
+            AudioCodec::Aac | AudioCodec::Flac => None,
+        }
+    }
+}
+
+/// Re-encodes one audio stream of the input while everything else is copied.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AudioTranscode {
+    /// Index of the stream in the input.
+    pub stream: usize,
+    pub codec: AudioCodec,
+    /// Kbit/s.
+    pub bitrate: Option<u32>,
+    pub channels: Option<u32>,
+    /// Keep the original stream and add the compatibility track right after it.
+    pub keep_original: bool,
+}
+
+impl AudioTranscode {
+    /// Parses a spec such as `ac3 640k 6ch keep` for the given stream.
+    pub fn parse(stream: &StreamInfo, spec: &str) -> Result<AudioTranscode, String> {
+        let spec = spec.trim().to_ascii_lowercase();
+        let mut words = spec.split_whitespace();
+        let codec = words
+            .next()
+            .and_then(AudioCodec::parse)
+            .ok_or_else(|| "start with a codec: ac3, eac3, aac or flac".to_string())?;
+        let mut transcode = AudioTranscode {
+            stream: stream.index,
+            codec,
+            bitrate: codec.default_bitrate(),
+            channels: None,
+            keep_original: false,
+        };
+        for word in words {
+            if word == "keep" {
+                transcode.keep_original = true;
+            } else if let Some(rate) = word.strip_suffix('k').and_then(|n| n.parse().ok()) {
+                transcode.bitrate = Some(rate);
+            } else if let Some(channels) = parse_channels(word) {
+                transcode.channels = Some(channels);
+            } else {
+                return Err(format!("unknown option \"{word}\""));
+            }
+        }
+        if transcode.channels.is_none() {
+            transcode.channels = codec.max_channels().filter(|max| stream.channels.is_some_and(|c| c > *max));
+        }
+        Ok(transcode)
+    }
+
+    /// Spec accepted by `parse` for this transcode.
+    pub fn to_spec(&self) -> String {
+        let mut spec = vec![self.codec.encoder().to_string()];
+        spec.extend(self.bitrate.map(|b| format!("{b}k")));
+        spec.extend(self.channels.map(|c| format!("{c}ch")));
+        if self.keep_original {
+            spec.push("keep".to_string());
+        }
+        spec.join(" ")
+    }
+
+    pub fn describe(&self) -> String {
+        let mut text = self.codec.label().to_string();
+        if let Some(bitrate) = self.bitrate {
+            text.push_str(&format!(" {bitrate}k"));
+        }
+        if let Some(channels) = self.channels {
+            text.push_str(&format!(" {}", channels_label(channels)));
+        }
+        if self.keep_original {
+            text.push_str(" + original");
+        }
+        text
+    }
+
+    /// Encoder options for the output stream `output`.
+    pub fn codec_args(&self, output: usize) -> Vec<String> {
+        let mut args = vec![format!("-c:{output}"), self.codec.encoder().to_string()];
+        if let Some(bitrate) = self.bitrate {
+            args.extend([format!("-b:{output}"), format!("{bitrate}k")]);
+        }
+        if let Some(channels) = self.channels {
+            args.extend([format!("-ac:{output}"), channels.to_string()]);
+        }
+        args
+    }
+}
+
+fn parse_channels(word: &str) -> Option<u32> {
+    match word {
+        "mono" => Some(1),
+        "stereo" => Some(2),
+        "5.1" => Some(6),
+        "7.1" => Some(8),
+        _ => word.strip_suffix("ch")?.parse().ok().filter(|c| (1..=8).contains(c)),
+    }
+}
+
+fn channels_label(channels: u32) -> String {
+    match channels {
+        1 => "mono".to_string(),
+        2 => "stereo".to_string(),
+        6 => "5.1".to_string(),
+        8 => "7.1".to_string(),
+        n => format!("{n}ch"),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SubtitleFormat {
+    Srt,
+    Ass,
+    WebVtt,
+    MovText,
+}
+
+impl SubtitleFormat {
+    /// Cycles through the formats, `None` meaning subtitles are copied when possible.
+    pub fn next(format: Option<SubtitleFormat>) -> Option<SubtitleFormat> {
+        match format {
+            None => Some(SubtitleFormat::Srt),
+            Some(SubtitleFormat::Srt) => Some(SubtitleFormat::Ass),
+            Some(SubtitleFormat::Ass) => Some(SubtitleFormat::WebVtt),
+            Some(SubtitleFormat::WebVtt) => Some(SubtitleFormat::MovText),
+            Some(SubtitleFormat::MovText) => None,
+        }
+    }
+
+    pub fn encoder(self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::Ass => "ass",
+            SubtitleFormat::WebVtt => "webvtt",
+            SubtitleFormat::MovText => "mov_text",
+        }
+    }
+
+    /// Codec name reported by ffprobe for streams in this format.
+    pub fn codec_name(self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "subrip",
+            SubtitleFormat::Ass => "ass",
+            SubtitleFormat::WebVtt => "webvtt",
+            SubtitleFormat::MovText => "mov_text",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "SRT",
+            SubtitleFormat::Ass => "ASS",
+            SubtitleFormat::WebVtt => "WebVTT",
+            SubtitleFormat::MovText => "mov_text",
+        }
+    }
+
+    /// Text format used when the container cannot hold a subtitle as is.
+    fn fallback(container: &str) -> SubtitleFormat {
+        match container {
+            "mp4" | "m4v" | "mov" => SubtitleFormat::MovText,
+            "webm" => SubtitleFormat::WebVtt,
+            _ => SubtitleFormat::Srt,
+        }
+    }
+}
+
+/// What a remux does with one subtitle stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtitleAction {
+    Copy,
+    Convert(SubtitleFormat),
+    Drop,
+}
+
+const TEXT_SUBTITLES: &[&str] = &["subrip", "srt", "ass", "ssa", "webvtt", "mov_text", "text"];
+
+pub fn is_text_subtitle(codec: &str) -> bool {
+    TEXT_SUBTITLES.contains(&codec)
+}
+
+fn container_accepts(container: &str, codec: &str) -> bool {
+    match container {
+        "mp4" | "m4v" | "mov" => matches!(codec, "mov_text" | "dvd_subtitle"),
+        "webm" => codec == "webvtt",
+        _ => codec != "mov_text",
+    }
+}
+
+/// Decides how a subtitle of `codec` is written to `container` when `wanted` is requested,
+/// with a note for the plan validator when the request cannot be followed.
+pub fn subtitle_action(codec: &str, container: &str, wanted: Option<SubtitleFormat>) -> (SubtitleAction, Option<String>) {
+    let mut note = None;
+    let mut target = wanted.filter(|f| f.codec_name() != codec);
+    if let Some(format) = target
+        && !container_accepts(container, format.codec_name())
+    {
+        let fallback = SubtitleFormat::fallback(container);
+        note = Some(format!("{} cannot be stored in .{container}, using {}", format.label(), fallback.label()));
+        target = Some(fallback).filter(|f| f.codec_name() != codec);
+    }
+    if target.is_none() && !container_accepts(container, codec) {
+        target = Some(SubtitleFormat::fallback(container));
+    }
+    match target {
+        None => (SubtitleAction::Copy, note),
+        Some(format) if is_text_subtitle(codec) => (SubtitleAction::Convert(format), note),
+        Some(format) if container_accepts(container, codec) => (
+            SubtitleAction::Copy,
+            Some(format!("{codec} is a bitmap subtitle and cannot be converted to {}, copied as is", format.label())),
+        ),
+        Some(_) => (
+            SubtitleAction::Drop,
+            Some(format!("{codec} is a bitmap subtitle that .{container} cannot hold, it is dropped")),
+        ),
+    }
+}
@@ -0,0 +1,206 @@
+use std::path::Path;
+
+use crate::{ffmpeg::probe, ffmpeg::progress::parse_timestamp, units::human_duration};
+
+use super::plan::Segment;
+
+/// Part of the input to keep; `end: None` runs to the end of the file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeRange {
+    pub start: f64,
+    pub end: Option<f64>,
+}
+
+/// A range whose start was moved back onto a keyframe so it can be cut without re-encoding.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SnappedRange {
+    pub requested: TimeRange,
+    pub start: f64,
+    /// False when no keyframe was found shortly before the start, which is then kept as is
+    /// and shows broken frames until the next keyframe.
+    pub on_keyframe: bool,
+}
+
+impl SnappedRange {
+    /// How much earlier than requested the output starts.
+    pub fn offset(&self) -> f64 {
+        self.requested.start - self.start
+    }
+
+    pub fn describe(&self) -> String {
+        let end = self.requested.end.map(format_time).unwrap_or_else(|| "end".to_string());
+        let mut text = format!("{} - {end}", format_time(self.start));
+        if self.offset() > 0.001 {
+            text.push_str(&format!(
+                " (starts {:.3}s before {} to land on a keyframe)",
+                self.offset(),
+                format_time(self.requested.start)
+            ));
+        }
+        if !self.on_keyframe {
+            text.push_str(" (no keyframe found before the start, it may show broken frames)");
+        }
+        text
+    }
+}
+
+/// Parses `start-end` ranges separated by commas, e.g. `1:30-45:00, 1:00:00-`.
+/// Times are `HH:MM:SS(.fff)`, `MM:SS` or seconds.
+pub fn parse_ranges(text: &str) -> Result<Vec<TimeRange>, String> {
+    let mut ranges = vec![];
+    for part in text.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (start, end) = part
+            .split_once('-')
+            .ok_or_else(|| format!("\"{part}\" is not a start-end range"))?;
+        let start = if start.trim().is_empty() { 0.0 } else { parse_time(start)? };
+        let end = if end.trim().is_empty() { None } else { Some(parse_time(end)?) };
+        if end.is_some_and(|end| end <= start) {
+            return Err(format!("\"{part}\" ends before it starts"));
+        }
+        ranges.push(TimeRange { start, end });
+    }
+    ranges.sort_by(|a, b| a.start.total_cmp(&b.start));
+    if ranges
+        .windows(2)
+        .any(|pair| pair[0].end.is_none_or(|end| end > pair[1].start))
+    {
+        return Err("ranges overlap".to_string());
+    }
+    Ok(ranges)
+}
+
+pub fn parse_time(text: &str) -> Result<f64, String> {
+    let text = text.trim();
+    let time = match text.matches(':').count() {
+        0 => text.parse::<f64>().ok().filter(|t| *t >= 0.0),
+        1 => parse_timestamp(&format!("0:{text}")).map(|d| d.as_secs_f64()),
+        _ => parse_timestamp(text).map(|d| d.as_secs_f64()),
+    };
+    time.ok_or_else(|| format!("invalid time \"{text}\""))
+}
+
+pub fn format_time(secs: f64) -> String {
+    let whole = std::time::Duration::from_secs_f64(secs.max(0.0));
+    format!("{}.{:03}", human_duration(whole), whole.subsec_millis())
+}
+
+/// Moves every range start back to the closest keyframe at or before it. Files without
+/// video can be cut anywhere.
+pub fn snap(path: &Path, ranges: &[TimeRange], has_video: bool) -> Result<Vec<SnappedRange>, String> {
+    if !has_video {
+        return snap_to(&[], ranges, false);
+    }
+    let starts: Vec<f64> = ranges.iter().map(|r| r.start).collect();
+    let keyframes = probe::keyframes_near(path, &starts).map_err(|err| format!("ffprobe failed: {err}"))?;
+    snap_to(&keyframes, ranges, true)
+}
+
+/// Snaps sorted ranges onto the sorted keyframes, refusing ranges that overlap once moved.
+fn snap_to(keyframes: &[f64], ranges: &[TimeRange], has_video: bool) -> Result<Vec<SnappedRange>, String> {
+    let snapped: Vec<SnappedRange> = ranges
+        .iter()
+        .map(|&requested| {
+            let keyframe = keyframes.iter().rev().find(|&&k| k <= requested.start + 0.001).copied();
+            SnappedRange {
+                requested,
+                start: keyframe.unwrap_or(requested.start),
+                on_keyframe: !has_video || keyframe.is_some() || requested.start < 0.001,
+            }
+        })
+        .collect();
+    for pair in snapped.windows(2) {
+        if pair[0].requested.end.is_none_or(|end| end > pair[1].start + 0.001) {
+            return Err(format!(
+                "{} moves back to the keyframe at {} and overlaps the previous range",
+                format_time(pair[1].requested.start),
+                format_time(pair[1].start)
+            ));
+        }
+    }
+    Ok(snapped)
+}
+
+pub fn segments(path: &Path, ranges: &[SnappedRange], duration: Option<f64>) -> Vec<Segment> {
+    ranges
+        .iter()
+        .map(|range| Segment {
+            path: path.to_path_buf(),
+            duration,
+            inpoint: (range.start > 0.0).then_some(range.start),
+            outpoint: range.requested.end,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_times() {
+        assert_eq!(parse_time("90").unwrap(), 90.0);
+        assert_eq!(parse_time("1:30").unwrap(), 90.0);
+        assert_eq!(parse_time(" 1:02:03.5 ").unwrap(), 3723.5);
+        assert!(parse_time("-4").is_err());
+        assert!(parse_time("abc").is_err());
+    }
+
+    #[test]
+    fn parses_and_sorts_ranges() {
+        let ranges = parse_ranges("1:00:00-, 1:30-45:00").unwrap();
+        assert_eq!(
+            ranges,
+            [TimeRange { start: 90.0, end: Some(2700.0) }, TimeRange { start: 3600.0, end: None }]
+        );
+        assert_eq!(parse_ranges("-10").unwrap(), [TimeRange { start: 0.0, end: Some(10.0) }]);
+        assert!(parse_ranges("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_bad_ranges() {
+        assert!(parse_ranges("20-10").is_err());
+        assert!(parse_ranges("30").is_err());
+        assert!(parse_ranges("0-40, 30-50").is_err());
+        assert!(parse_ranges("10-, 30-50").is_err());
+    }
+
+    #[test]
+    fn snaps_back_to_the_previous_keyframe() {
+        let ranges = parse_ranges("12.5-20, 40-").unwrap();
+        let snapped = snap_to(&[0.0, 10.0, 20.0, 30.0, 40.0], &ranges, true).unwrap();
+        assert_eq!(snapped[0].start, 10.0);
+        assert_eq!(snapped[0].offset(), 2.5);
+        assert_eq!(snapped[1].start, 40.0);
+        assert!(snapped.iter().all(|r| r.on_keyframe));
+    }
+
+    #[test]
+    fn flags_starts_without_keyframe() {
+        let ranges = parse_ranges("0-5, 100-110").unwrap();
+        let snapped = snap_to(&[104.0], &ranges, true).unwrap();
+        assert!(snapped[0].on_keyframe);
+        assert!(!snapped[1].on_keyframe);
+        assert_eq!(snapped[1].start, 100.0);
+        assert!(snapped[1].describe().contains("no keyframe"));
+
+        let audio_only = snap_to(&[], &ranges, false).unwrap();
+        assert!(audio_only.iter().all(|r| r.on_keyframe && r.offset() == 0.0));
+    }
+
+    #[test]
+    fn refuses_ranges_overlapping_once_snapped() {
+        let ranges = parse_ranges("0-12, 15-20").unwrap();
+        assert!(snap_to(&[0.0, 10.0], &ranges, true).is_err());
+        assert!(snap_to(&[0.0, 12.0], &ranges, true).is_ok());
+    }
+
+    #[test]
+    fn segments_skip_the_inpoint_at_zero() {
+        let ranges = snap_to(&[0.0, 60.0], &parse_ranges("0-30, 61-").unwrap(), true).unwrap();
+        let segments = segments(Path::new("movie.mkv"), &ranges, Some(120.0));
+        assert_eq!(segments[0].inpoint, None);
+        assert_eq!(segments[0].outpoint, Some(30.0));
+        assert_eq!(segments[1].inpoint, Some(60.0));
+        assert_eq!(segments[1].outpoint, None);
+    }
+}
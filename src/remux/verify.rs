@@ -0,0 +1,321 @@
+use std::{fs, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ffmpeg::probe::{self, ProbeInfo, StreamInfo},
+    units::human_bytes,
+};
+
+use super::{
+    plan::{Encoding, RemuxPlan},
+    sidecar::SidecarKind,
+    split::{self, SplitMode},
+};
+
+/// Output durations may differ from the plan by this much, or by `DURATION_RATIO` of it
+/// when longer, since cuts and muxers round to packet boundaries.
+const DURATION_TOLERANCE: f64 = 2.0;
+const DURATION_RATIO: f64 = 0.01;
+
+/// Outcome of comparing a finished remux with its plan.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Verdict {
+    pub problems: Vec<String>,
+    /// Worth knowing about an output that is still right, such as parts over their size.
+    #[serde(default)]
+    pub notes: Vec<String>,
+}
+
+impl Verdict {
+    pub fn passed(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    pub fn describe(&self) -> String {
+        match self.problems.as_slice() {
+            [] if self.notes.is_empty() => "verified".to_string(),
+            [] => format!("verified, {}", self.notes.join(", ")),
+            [problem] => format!("verification failed: {problem}"),
+            [first, rest @ ..] => format!("verification failed: {first} (+{} more)", rest.len()),
+        }
+    }
+}
+
+/// What the plan says one output stream should look like.
+struct Expected {
+    label: String,
+    codec_type: &'static str,
+    codec: Option<String>,
+    language: Option<String>,
+    default: Option<bool>,
+    forced: Option<bool>,
+}
+
+fn expected_streams(plan: &RemuxPlan) -> Vec<Expected> {
+    // Only Matroska keeps every disposition flag, other muxers rewrite them.
+    let matroska = matches!(plan.container().as_str(), "mkv" | "mka" | "mks" | "webm");
+    let mut expected = vec![];
+    for output in plan.output_streams() {
+        let Some(source) = plan.source.streams.iter().find(|s| s.index == output.source) else {
+            continue;
+        };
+        let (codec, default, forced) = match &output.encoding {
+            Encoding::Copy => (
+                source.codec_name.clone(),
+                Some(source.disposition.default == 1),
+                Some(source.disposition.forced == 1),
+            ),
+            Encoding::Audio(transcode) if transcode.keep_original => {
+                (Some(transcode.codec.encoder().to_string()), Some(false), Some(false))
+            }
+            Encoding::Audio(transcode) => (Some(transcode.codec.encoder().to_string()), None, None),
+            Encoding::Subtitle(format) => (Some(format.codec_name().to_string()), None, None),
+        };
+        let edit = plan.edit_of(&output);
+        expected.push(Expected {
+            label: format!("stream {}", output.source),
+            codec_type: stream_type(source),
+            codec,
+            language: edit
+                .and_then(|e| e.language.clone())
+                .or_else(|| source.language().map(str::to_string))
+                .filter(|l| l != "und"),
+            default: edit.and_then(|e| e.default).or(default).filter(|_| matroska),
+            forced: edit.and_then(|e| e.forced).or(forced).filter(|_| matroska),
+        });
+    }
+    for (sidecar, format) in plan.muxed_sidecars() {
+        let codec = match format {
+            Some(format) => Some(format.codec_name().to_string()),
+            None => sidecar.subtitle_codec().map(str::to_string),
+        };
+        expected.push(Expected {
+            label: sidecar.file_name(),
+            codec_type: match sidecar.kind {
+                SidecarKind::Subtitle => "subtitle",
+                SidecarKind::Audio => "audio",
+            },
+            codec,
+            language: sidecar.language.clone(),
+            default: Some(sidecar.default).filter(|_| matroska),
+            forced: Some(sidecar.forced).filter(|_| matroska),
+        });
+    }
+    expected
+}
+
+fn stream_type(stream: &StreamInfo) -> &'static str {
+    match stream.codec_type() {
+        "video" => "video",
+        "audio" => "audio",
+        "subtitle" => "subtitle",
+        "attachment" => "attachment",
+        _ => "data",
+    }
+}
+
+/// Probes what the remux wrote and compares it with the plan.
+pub fn verify(plan: &RemuxPlan) -> Verdict {
+    let paths: Vec<PathBuf> = split::expand(&plan.written_output());
+    let mut verdict = Verdict::default();
+    if paths.is_empty() || !paths.iter().all(|p| p.is_file()) {
+        verdict.problems.push("output file missing".to_string());
+        return verdict;
+    }
+    let mut probes = vec![];
+    for path in &paths {
+        match probe::probe(path) {
+            Ok(probe) => probes.push(probe),
+            Err(err) => {
+                verdict.problems.push(format!("cannot probe {}: {err}", path.display()));
+                return verdict;
+            }
+        }
+    }
+
+    let mut verdict = compare(plan, &paths, &probes);
+
+    // Size splits are cut from the average bitrate, a bitrate peak makes a part larger.
+    if let Some(SplitMode::Size(limit)) = plan.split {
+        let over: Vec<String> = paths
+            .iter()
+            .filter(|p| fs::metadata(p).is_ok_and(|m| m.len() > limit))
+            .map(|p| p.file_name().unwrap_or_default().to_string_lossy().into_owned())
+            .collect();
+        if !over.is_empty() {
+            let limit = human_bytes(limit as f64);
+            verdict.notes.push(format!("{} part(s) over {limit}: {}", over.len(), over.join(", ")));
+        }
+    }
+    verdict
+}
+
+/// Compares the probes of the files a remux wrote with its plan.
+fn compare(plan: &RemuxPlan, paths: &[PathBuf], probes: &[ProbeInfo]) -> Verdict {
+    let mut verdict = Verdict::default();
+    let expected = expected_streams(plan);
+    for (path, probe) in paths.iter().zip(probes) {
+        let prefix = if paths.len() > 1 {
+            format!("{}: ", path.file_name().unwrap_or_default().to_string_lossy())
+        } else {
+            String::new()
+        };
+        compare_streams(&expected, probe, &prefix, &mut verdict.problems);
+    }
+
+    let written: Option<f64> = probes.iter().map(ProbeInfo::duration).sum();
+    if let (Some(planned), Some(written)) = (plan.duration(), written) {
+        let tolerance = DURATION_TOLERANCE.max(planned * DURATION_RATIO);
+        if (planned - written).abs() > tolerance {
+            verdict.problems.push(format!("duration {written:.1}s instead of {planned:.1}s"));
+        }
+    }
+
+    if let [probe] = probes {
+        let chapters = if !plan.chapters.is_empty() {
+            Some(plan.chapters.len())
+        } else if plan.segments.is_empty() {
+            Some(plan.source.chapters.len())
+        } else {
+            // Trimmed outputs only keep the chapters inside the kept ranges.
+            None
+        };
+        if let Some(chapters) = chapters
+            && probe.chapters.len() != chapters
+        {
+            verdict.problems.push(format!("{} chapters instead of {chapters}", probe.chapters.len()));
+        }
+    }
+    verdict
+}
+
+/// Compares the streams of an output with the plan. Data streams are left out on both sides:
+/// the MP4 muxer adds a chapter text track of its own, and timecode tracks come and go.
+fn compare_streams(expected: &[Expected], probe: &ProbeInfo, prefix: &str, problems: &mut Vec<String>) {
+    let expected: Vec<&Expected> = expected.iter().filter(|e| e.codec_type != "data").collect();
+    let written: Vec<(usize, &StreamInfo)> =
+        probe.streams.iter().enumerate().filter(|(_, s)| stream_type(s) != "data").collect();
+    if written.len() != expected.len() {
+        problems.push(format!("{prefix}{} streams instead of {}", written.len(), expected.len()));
+        return;
+    }
+    for (want, (i, got)) in expected.iter().zip(written) {
+        let mut differences = vec![];
+        if stream_type(got) != want.codec_type {
+            differences.push(format!("{} instead of {}", got.codec_type(), want.codec_type));
+        }
+        if let Some(codec) = &want.codec
+            && got.codec_name() != codec
+        {
+            differences.push(format!("codec {} instead of {codec}", got.codec_name()));
+        }
+        if let Some(language) = &want.language
+            && got.language() != Some(language.as_str())
+        {
+            differences.push(format!("language {} instead of {language}", got.language().unwrap_or("und")));
+        }
+        if let Some(default) = want.default
+            && (got.disposition.default == 1) != default
+        {
+            differences.push(format!("default flag {}", if default { "lost" } else { "added" }));
+        }
+        if let Some(forced) = want.forced
+            && (got.disposition.forced == 1) != forced
+        {
+            differences.push(format!("forced flag {}", if forced { "lost" } else { "added" }));
+        }
+        if !differences.is_empty() {
+            problems.push(format!("{prefix}output stream {i} ({}): {}", want.label, differences.join(", ")));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"{
+        "streams": [
+            {"index": 0, "codec_type": "video", "codec_name": "h264", "disposition": {"default": 1}},
+            {"index": 1, "codec_type": "audio", "codec_name": "aac", "tags": {"language": "eng"},
+             "disposition": {"default": 1}},
+            {"index": 2, "codec_type": "audio", "codec_name": "ac3", "tags": {"language": "fre"}}
+        ],
+        "format": {"duration": "5400.000000"},
+        "chapters": [
+            {"id": 0, "start_time": "0.000000", "end_time": "1800.000000"},
+            {"id": 1, "start_time": "1800.000000", "end_time": "3600.000000"},
+            {"id": 2, "start_time": "3600.000000", "end_time": "5400.000000"}
+        ]
+    }"#;
+
+    /// ffprobe of an MP4 written from SOURCE, with the chapter track the muxer adds.
+    const MP4: &str = r#"{
+        "streams": [
+            {"index": 0, "codec_type": "video", "codec_name": "h264", "disposition": {"default": 1}},
+            {"index": 1, "codec_type": "audio", "codec_name": "aac", "tags": {"language": "eng"},
+             "disposition": {"default": 1}},
+            {"index": 2, "codec_type": "audio", "codec_name": "ac3", "tags": {"language": "fre"}},
+            {"index": 3, "codec_type": "data", "codec_name": "bin_data", "tags": {"handler_name": "Chapters"}}
+        ],
+        "format": {"duration": "5400.021000"},
+        "chapters": [
+            {"id": 0, "start_time": "0.000000", "end_time": "1800.000000"},
+            {"id": 1, "start_time": "1800.000000", "end_time": "3600.000000"},
+            {"id": 2, "start_time": "3600.000000", "end_time": "5400.021000"}
+        ]
+    }"#;
+
+    fn verdict(output: &str, written: &ProbeInfo) -> Verdict {
+        let source = serde_json::from_str(SOURCE).unwrap();
+        let plan = RemuxPlan::new(PathBuf::from("in.mkv"), PathBuf::from(output), source);
+        compare(&plan, std::slice::from_ref(&plan.output), std::slice::from_ref(written))
+    }
+
+    #[test]
+    fn ignores_the_chapter_track_of_mp4_outputs() {
+        let written: ProbeInfo = serde_json::from_str(MP4).unwrap();
+        assert!(verdict("out.mp4", &written).passed(), "{:?}", verdict("out.mp4", &written));
+        let mut dropped = written.clone();
+        dropped.streams.remove(2);
+        assert_eq!(verdict("out.mp4", &dropped).problems, ["2 streams instead of 3"]);
+    }
+
+    #[test]
+    fn allows_durations_within_the_tolerance() {
+        let mut written: ProbeInfo = serde_json::from_str(MP4).unwrap();
+        // One percent of an hour and a half.
+        written.format.duration = Some("5452.0".to_string());
+        assert!(verdict("out.mp4", &written).passed());
+        written.format.duration = Some("5300.0".to_string());
+        assert_eq!(verdict("out.mp4", &written).problems, ["duration 5300.0s instead of 5400.0s"]);
+    }
+
+    #[test]
+    fn reports_language_and_flag_changes() {
+        let mut written: ProbeInfo = serde_json::from_str(MP4).unwrap();
+        written.streams.pop();
+        written.streams[1].tags.remove("language");
+        written.streams[1].disposition.default = 0;
+        written.streams[2].disposition.forced = 1;
+        // Only Matroska is trusted with disposition flags.
+        assert_eq!(verdict("out.mp4", &written).problems, ["output stream 1 (stream 1): language und instead of eng"]);
+        assert_eq!(
+            verdict("out.mkv", &written).problems,
+            [
+                "output stream 1 (stream 1): language und instead of eng, default flag lost",
+                "output stream 2 (stream 2): forced flag added",
+            ]
+        );
+    }
+
+    #[test]
+    fn counts_chapters() {
+        let mut written: ProbeInfo = serde_json::from_str(MP4).unwrap();
+        written.chapters.pop();
+        assert_eq!(verdict("out.mp4", &written).problems, ["2 chapters instead of 3"]);
+        written.chapters.clear();
+        assert_eq!(verdict("out.mp4", &written).problems, ["0 chapters instead of 3"]);
+    }
+}
@@ -0,0 +1,269 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+        Arc,
+    },
+    thread,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    events::events::AppEvent,
+    ffmpeg::probe::{self, ProbeInfo},
+    units::{human_bytes, parse_bytes},
+};
+
+/// How often the walk reports how many files it found.
+const FOUND_EVERY: usize = 25;
+
+/// Which files of a folder scan join the batch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanFilter {
+    /// Globs of which one must match when not empty. Patterns with a `/` are matched against
+    /// the path relative to the scanned folder, others against each of its components.
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    /// Extensions without the dot, any file when empty.
+    pub extensions: Vec<String>,
+    pub min_size: u64,
+}
+
+impl Default for ScanFilter {
+    fn default() -> Self {
+        Self {
+            include: vec![],
+            exclude: vec!["*sample*".to_string()],
+            extensions: ["mkv", "mp4", "m4v", "mov", "avi", "ts", "m2ts", "webm"].map(String::from).to_vec(),
+            min_size: 0,
+        }
+    }
+}
+
+impl ScanFilter {
+    /// Parses `include=*S01*, *S02*; exclude=*sample*; ext=mkv, mp4; min=100MB`, left out parts
+    /// being empty.
+    pub fn parse(text: &str) -> Result<ScanFilter, String> {
+        let mut filter = ScanFilter { include: vec![], exclude: vec![], extensions: vec![], min_size: 0 };
+        let list = |value: &str| -> Vec<String> {
+            value.split(',').map(str::trim).filter(|v| !v.is_empty()).map(String::from).collect()
+        };
+        for part in text.split(';').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = part.split_once('=').ok_or_else(|| format!("expected key=value in \"{part}\""))?;
+            match key.trim().to_ascii_lowercase().as_str() {
+                "include" => filter.include = list(value),
+                "exclude" => filter.exclude = list(value),
+                "ext" | "extensions" => {
+                    filter.extensions = list(value).iter().map(|e| e.trim_start_matches('.').to_ascii_lowercase()).collect();
+                }
+                "min" | "min_size" => {
+                    filter.min_size = parse_bytes(value).ok_or_else(|| format!("invalid size \"{}\"", value.trim()))?;
+                }
+                other => return Err(format!("unknown setting \"{other}\"")),
+            }
+        }
+        Ok(filter)
+    }
+
+    /// Text accepted by `parse` for this filter.
+    pub fn to_spec(&self) -> String {
+        format!(
+            "include={}; exclude={}; ext={}; min={}",
+            self.include.join(", "),
+            self.exclude.join(", "),
+            self.extensions.join(", "),
+            match self.min_size {
+                0 => "0".to_string(),
+                size => format!("{}MB", size / (1024 * 1024)),
+            }
+        )
+    }
+
+    pub fn describe(&self) -> String {
+        let mut parts = vec![];
+        if !self.extensions.is_empty() {
+            parts.push(format!(".{}", self.extensions.join(" .")));
+        }
+        if !self.include.is_empty() {
+            parts.push(format!("matching {}", self.include.join(" or ")));
+        }
+        if !self.exclude.is_empty() {
+            parts.push(format!("not {}", self.exclude.join(" nor ")));
+        }
+        if self.min_size > 0 {
+            parts.push(format!("from {}", human_bytes(self.min_size as f64)));
+        }
+        if parts.is_empty() { "every file".to_string() } else { parts.join(", ") }
+    }
+
+    fn accepts(&self, relative: &Path, size: u64) -> bool {
+        let ext = relative.extension().unwrap_or_default().to_string_lossy().to_ascii_lowercase();
+        let matches = |pattern: &String| {
+            let pattern = pattern.to_lowercase();
+            let relative = relative.to_string_lossy().to_lowercase();
+            match pattern.contains('/') {
+                true => glob(pattern.as_bytes(), relative.as_bytes()),
+                false => relative.split('/').any(|part| glob(pattern.as_bytes(), part.as_bytes())),
+            }
+        };
+        size >= self.min_size
+            && (self.extensions.is_empty() || self.extensions.contains(&ext))
+            && (self.include.is_empty() || self.include.iter().any(matches))
+            && !self.exclude.iter().any(matches)
+    }
+}
+
+/// `*` matches within a path component, `**` across them and `?` one character.
+fn glob(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => (0..=text.len()).any(|i| glob(rest, &text[i..])),
+        [b'*', rest @ ..] => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != b'/')
+            .any(|i| glob(rest, &text[i..])),
+        [b'?', rest @ ..] => matches!(text, [c, ..] if *c != b'/') && glob(rest, &text[1..]),
+        [c, rest @ ..] => text.first() == Some(c) && glob(rest, &text[1..]),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ScanEvent {
+    /// Files accepted by the filter so far.
+    Found(usize),
+    /// `None` when ffprobe failed or found neither video nor audio.
+    Probed(PathBuf, Option<Box<ProbeInfo>>),
+    Finished { cancelled: bool },
+}
+
+/// Counters of the scan shown while it runs.
+#[derive(Debug, Clone, Default)]
+pub struct ScanProgress {
+    pub root: PathBuf,
+    pub found: usize,
+    pub probed: usize,
+    pub added: usize,
+    pub running: bool,
+}
+
+impl ScanProgress {
+    pub fn describe(&self) -> String {
+        format!("{} found, {} probed, {} added", self.found, self.probed, self.added)
+    }
+}
+
+/// Number of a scan, which tells the events of a cancelled scan from those of the next one.
+pub type ScanId = u64;
+
+pub struct ScanHandle {
+    pub id: ScanId,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl ScanHandle {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Walks `root` for files accepted by the filter, then probes them one by one, reporting on
+/// the event queue. Hidden entries and symlinked folders are skipped.
+pub fn spawn(id: ScanId, root: PathBuf, filter: ScanFilter, sender: Sender<AppEvent>) -> ScanHandle {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let handle = ScanHandle { id, cancelled: Arc::clone(&cancelled) };
+    thread::spawn(move || {
+        let send = |event| sender.send(AppEvent::Scan(id, event)).is_ok();
+        let mut found = vec![];
+        let mut dirs = vec![root.clone()];
+        while let Some(dir) = dirs.pop() {
+            if cancelled.load(Ordering::SeqCst) {
+                break;
+            }
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            let entries: Vec<_> = entries.filter_map(Result::ok).collect();
+            for entry in entries {
+                let path = entry.path();
+                if entry.file_name().to_string_lossy().starts_with('.') {
+                    continue;
+                }
+                match entry.file_type() {
+                    Ok(kind) if kind.is_dir() => dirs.push(path),
+                    Ok(_) => {
+                        let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+                        let relative = path.strip_prefix(&root).unwrap_or(&path);
+                        if path.is_file() && filter.accepts(relative, size) {
+                            found.push(path);
+                            if found.len() % FOUND_EVERY == 0 {
+                                send(ScanEvent::Found(found.len()));
+                            }
+                        }
+                    }
+                    Err(_) => {}
+                }
+            }
+        }
+        found.sort();
+        send(ScanEvent::Found(found.len()));
+
+        for path in found {
+            if cancelled.load(Ordering::SeqCst) {
+                break;
+            }
+            let probe = probe::probe(&path)
+                .ok()
+                .filter(|p| p.streams.iter().any(|s| matches!(s.codec_type(), "video" | "audio")));
+            if !send(ScanEvent::Probed(path, probe.map(Box::new))) {
+                return;
+            }
+        }
+        send(ScanEvent::Finished { cancelled: cancelled.load(Ordering::SeqCst) });
+    });
+    handle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs_stay_within_components_unless_doubled() {
+        assert!(glob(b"*s01e0?*", b"show.s01e03.1080p.mkv"));
+        assert!(!glob(b"*s01e0?*", b"show.s01e13.mkv"));
+        assert!(!glob(b"season 1/*.mkv", b"season 1/extras/ep.mkv"));
+        assert!(glob(b"season 1/**.mkv", b"season 1/extras/ep.mkv"));
+        assert!(!glob(b"a?b", b"a/b"));
+        assert!(glob(b"*", b""));
+    }
+
+    #[test]
+    fn parses_filters_back_from_their_spec() {
+        let filter = ScanFilter::parse("include=*S01*, *S02*; exclude=*sample*; ext=.MKV, mp4; min=100MB").unwrap();
+        assert_eq!(filter.include, ["*S01*", "*S02*"]);
+        assert_eq!(filter.extensions, ["mkv", "mp4"]);
+        assert_eq!(filter.min_size, 100 * 1024 * 1024);
+        assert_eq!(ScanFilter::parse(&filter.to_spec()).unwrap().to_spec(), filter.to_spec());
+        assert_eq!(ScanFilter::parse("").unwrap().describe(), "every file");
+        assert!(ScanFilter::parse("min=lots").unwrap_err().contains("invalid size"));
+        assert!(ScanFilter::parse("depth=2").unwrap_err().contains("unknown setting"));
+        assert!(ScanFilter::parse("mkv").is_err());
+    }
+
+    #[test]
+    fn accepts_files_by_extension_size_and_globs() {
+        let filter = ScanFilter::parse("include=*S01*; exclude=*sample*; ext=mkv; min=1MB").unwrap();
+        let mb = 1024 * 1024;
+        assert!(filter.accepts(Path::new("Show/Season 1/Show.S01E01.mkv"), 700 * mb));
+        // Patterns without a slash match any component, folders included.
+        assert!(filter.accepts(Path::new("Show S01/e01.MKV"), 700 * mb));
+        assert!(!filter.accepts(Path::new("Show/Show.S01E01.sample.mkv"), 700 * mb));
+        assert!(!filter.accepts(Path::new("Show/Sample/Show.S01E01.mkv"), 700 * mb));
+        assert!(!filter.accepts(Path::new("Show/Show.S01E01.mp4"), 700 * mb));
+        assert!(!filter.accepts(Path::new("Show/Show.S01E01.mkv"), mb / 2));
+        assert!(!filter.accepts(Path::new("Show/Show.S02E01.mkv"), 700 * mb));
+    }
+}
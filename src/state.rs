@@ -1,3 +1,57 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::PathBuf,
+};
+
+use crate::{
+    components::{batch::BatchPreview, dry_run::DryRun, history::HistoryView, prompt::Prompt, text_input::TextInput},
+    ffmpeg::probe::ProbeInfo,
+    jobs::{job::JobId, queue::JobQueue},
+    remux::{rules::Rule, sidecar::Sidecar, split::SplitMode, transcode::{AudioTranscode, SubtitleFormat}, trim::SnappedRange},
+    scan::ScanProgress,
+};
+
 pub struct AppState {
     pub selected_file: Option<String>,
-}
\ No newline at end of file
+    /// Files marked in the file browser, in path order.
+    pub marked_files: BTreeSet<PathBuf>,
+    /// Probes of marked files made by a folder scan, reused when the batch is planned.
+    pub probes: HashMap<PathBuf, ProbeInfo>,
+    pub scan: Option<ScanProgress>,
+    pub status: Option<String>,
+    pub jobs: JobQueue,
+    /// Job shown in the remux pane, the last one started.
+    pub current_job: Option<JobId>,
+    /// Position in the list of the Jobs pane.
+    pub job_cursor: usize,
+    pub prompt: Option<Prompt>,
+    pub dry_run: Option<DryRun>,
+    /// Batch waiting to be reviewed before it is queued.
+    pub batch: Option<BatchPreview>,
+    pub history: Option<HistoryView>,
+    pub text_input: Option<TextInput>,
+    /// Probe of the selected file, refreshed when the selection changes.
+    pub probe: Option<ProbeInfo>,
+    pub output_preview: Option<Result<PathBuf, String>>,
+    /// Subtitle/audio files found next to the selected file.
+    pub sidecars: Vec<Sidecar>,
+    pub mux_sidecars: bool,
+    /// Whether the selected MP4 has its index first, `None` for other files.
+    pub faststart: Option<bool>,
+    /// Position in the stream list of the info pane.
+    pub stream_cursor: usize,
+    /// Stream indexes marked in the info pane.
+    pub selected_streams: BTreeSet<usize>,
+    /// Ranges of the selected file kept by the next remux, already snapped to keyframes.
+    pub trim: Vec<SnappedRange>,
+    /// Audio streams of the selected file re-encoded by the next remux.
+    pub audio: Vec<AudioTranscode>,
+    /// Kept across selections, like the output template.
+    pub split: Option<SplitMode>,
+    /// Text subtitle format requested for the next remuxes.
+    pub subtitles: Option<SubtitleFormat>,
+    /// Remuxes replace the selected file instead of writing next to it.
+    pub in_place: bool,
+    /// Parsed `track_rules` of the config.
+    pub rules: Vec<Rule>,
+}
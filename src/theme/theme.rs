@@ -1,4 +1,4 @@
-use ratatui::style::{palette::tailwind::{BLUE, CYAN, GRAY, SLATE}, Color, Modifier, Style};
+use ratatui::style::{palette::tailwind::{AMBER, BLUE, CYAN, GRAY, SLATE}, Color, Modifier, Style};
 
 #[derive(Debug, Clone, Copy)]
 pub struct Theme {
@@ -6,6 +6,7 @@ pub struct Theme {
     pub background: Color,
     pub highlight: Style,
     pub directory: Color,
+    pub marked: Color,
 }
 
 const DEFAULT: Theme = Theme {
@@ -13,6 +14,7 @@ const DEFAULT: Theme = Theme {
     background: BLUE.c900,
     highlight: Style::new().bg(CYAN.c500).add_modifier(Modifier::BOLD),
     directory: SLATE.c200,
+    marked: AMBER.c300,
 };
 
 impl Default for Theme {